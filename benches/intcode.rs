@@ -0,0 +1,89 @@
+use aoc_2019::intcode::asm::assemble;
+use aoc_2019::intcode::compile::Compiled;
+use aoc_2019::intcode::{interpret, run, Machine, State};
+use async_std::prelude::*;
+use async_std::stream;
+use async_std::task::block_on;
+use criterion::{criterion_group, criterion_main, Criterion};
+use futures_util::pin_mut;
+
+// Sums numbers from input down to 1, outputting partial sum every 1000
+// iterations
+const SUM: &str = "
+        READ [n]
+loop:   ADD [acc], [n], [acc]
+        ADD [n], #-1, [n]
+        ADD [tick], #-1, [tick]
+        JMPT [tick], #skip
+        WRT [acc]
+        ADD #1000, #0, [tick]
+skip:   JMPT [n], #loop
+        WRT [acc]
+        EXIT
+n:      DATA 0
+acc:    DATA 0
+tick:   DATA 1000
+";
+
+const N: i128 = 100_000;
+
+fn run_machine(program: &[i128], cache: bool) -> i128 {
+    let mut machine = Machine::new(program.to_vec());
+    machine.set_decode_cache(cache);
+    machine.input(N);
+
+    let mut last = 0;
+    loop {
+        match machine.run_until_event().unwrap() {
+            State::Output(val) => last = val,
+            State::Halted => return last,
+            state => panic!("Unexpected state: {:?}", state),
+        }
+    }
+}
+
+fn run_interpret(program: &[i128]) -> i128 {
+    block_on(async {
+        let outputs = interpret(program.to_vec(), stream::once(N));
+        pin_mut!(outputs);
+        outputs.fold(0, |_, val| val).await
+    })
+}
+
+fn run_sync(program: &[i128]) -> i128 {
+    run(program.to_vec(), Some(N)).last().unwrap()
+}
+
+fn run_compiled(compiled: &Compiled) -> i128 {
+    let mut machine = compiled.start();
+    machine.input(N);
+
+    let mut last = 0;
+    loop {
+        match machine.run_until_event().unwrap() {
+            State::Output(val) => last = val,
+            State::Halted => return last,
+            state => panic!("Unexpected state: {:?}", state),
+        }
+    }
+}
+
+fn intcode_benchmark(c: &mut Criterion) {
+    let program = assemble(SUM).unwrap();
+    assert_eq!(N * (N + 1) / 2, run_machine(&program, true));
+
+    let mut group = c.benchmark_group("sum");
+    group.bench_function("interpret", |b| b.iter(|| run_interpret(&program)));
+    group.bench_function("run", |b| b.iter(|| run_sync(&program)));
+    group.bench_function("machine uncached", |b| {
+        b.iter(|| run_machine(&program, false))
+    });
+    group.bench_function("machine cached", |b| b.iter(|| run_machine(&program, true)));
+    let compiled = Compiled::new(program.clone());
+    assert_eq!(N * (N + 1) / 2, run_compiled(&compiled));
+    group.bench_function("compiled", |b| b.iter(|| run_compiled(&compiled)));
+    group.finish();
+}
+
+criterion_group!(benches, intcode_benchmark);
+criterion_main!(benches);
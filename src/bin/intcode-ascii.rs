@@ -0,0 +1,50 @@
+use aoc_2019::intcode::parse;
+use aoc_2019::intcode::session::Session;
+use async_std::io::{stdin, stdout, BufReader};
+use async_std::prelude::*;
+use std::env::args;
+use std::process::exit;
+
+#[tokio::main]
+async fn main() {
+    let (path, script) = match (args().nth(1), args().nth(2)) {
+        (Some(path), script) => (path, script),
+        (None, _) => {
+            eprintln!("Usage: intcode-ascii <program> [script]");
+            exit(1);
+        }
+    };
+
+    let program = parse::load(&path).unwrap_or_else(|err| {
+        eprintln!("{}: {}", path, err);
+        exit(1);
+    });
+    let mut session = Session::new(program);
+    let mut commands = BufReader::new(stdin()).lines();
+
+    // Script is replayed just like loaded session, unreadable script is
+    // reported and the program started fresh
+    let intro = match script {
+        Some(script) => session.load(&script).unwrap_or_else(|err| {
+            eprintln!("{}: {}", script, err);
+            session.start()
+        }),
+        None => session.start(),
+    };
+    println!("{}", intro);
+
+    loop {
+        print!("> ");
+        stdout().flush().await.unwrap();
+
+        let command = match commands.next().await {
+            Some(command) => command.unwrap(),
+            None => break,
+        };
+
+        match session.execute(&command) {
+            Some(response) => println!("{}", response),
+            None => break,
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use aoc_2019::intcode::asm::assemble;
+use async_std::io::stdin;
+use async_std::prelude::*;
+use std::process::exit;
+
+#[tokio::main]
+async fn main() {
+    let mut source = String::new();
+    stdin().read_to_string(&mut source).await.unwrap();
+
+    match assemble(&source) {
+        Ok(program) => {
+            let program: Vec<_> = program.iter().map(i128::to_string).collect();
+            println!("{}", program.join(","));
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            exit(1);
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use aoc_2019::intcode::{parse, Machine, Outputs};
+use std::env::args;
+use std::process::exit;
+
+fn main() {
+    let mut args = args().skip(1).peekable();
+    let json = args.peek().map(String::as_str) == Some("--json");
+    if json {
+        args.next();
+    }
+
+    let path = match args.next() {
+        Some(path) => path,
+        None => {
+            eprintln!("Usage: intcode-coverage [--json] <program> [input...]");
+            exit(1);
+        }
+    };
+
+    let program = parse::load(&path).unwrap_or_else(|err| {
+        eprintln!("{}: {}", path, err);
+        exit(1);
+    });
+    let mut machine: Machine = Machine::new(program);
+    machine.enable_coverage();
+    let mut input = args.map(|arg| arg.parse().unwrap());
+    let mut outputs = Outputs::new(machine, move || input.next());
+
+    // Program outputs are not a part of report, so they go to stderr
+    for res in &mut outputs {
+        match res {
+            Ok(val) => eprintln!("{}", val),
+            Err(err) => eprintln!("{}", err),
+        }
+    }
+
+    let machine = outputs.into_machine();
+    let coverage = machine.coverage().unwrap();
+    if json {
+        println!("{}", coverage.to_json());
+    } else {
+        print!("{}", coverage);
+    }
+}
@@ -0,0 +1,40 @@
+use aoc_2019::intcode::debugger::Debugger;
+use aoc_2019::intcode::parse;
+use async_std::io::{stdin, stdout, BufReader};
+use async_std::prelude::*;
+use std::env::args;
+use std::process::exit;
+
+#[tokio::main]
+async fn main() {
+    let path = match args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("Usage: intcode-debug <program>");
+            exit(1);
+        }
+    };
+
+    let program = parse::load(&path).unwrap_or_else(|err| {
+        eprintln!("{}: {}", path, err);
+        exit(1);
+    });
+    let mut debugger = Debugger::new(program);
+    let mut commands = BufReader::new(stdin()).lines();
+
+    loop {
+        print!("> ");
+        stdout().flush().await.unwrap();
+
+        let command = match commands.next().await {
+            Some(command) => command.unwrap(),
+            None => break,
+        };
+
+        match debugger.execute(&command) {
+            Some(response) if response.is_empty() => (),
+            Some(response) => println!("{}", response),
+            None => break,
+        }
+    }
+}
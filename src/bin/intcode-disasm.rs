@@ -0,0 +1,11 @@
+use aoc_2019::intcode::disasm::disassemble;
+use aoc_2019::intcode::parse_program;
+use async_std::io::{stdin, BufReader};
+use async_std::prelude::*;
+
+#[tokio::main]
+async fn main() {
+    let mut input = BufReader::new(stdin()).lines().filter_map(Result::ok);
+    let program = parse_program(&mut input).await;
+    print!("{}", disassemble(&program));
+}
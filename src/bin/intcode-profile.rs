@@ -0,0 +1,37 @@
+use aoc_2019::intcode::{parse, Machine, Outputs};
+use std::env::args;
+use std::process::exit;
+
+// Number of hot spots shown in report
+const HOT_SPOTS: usize = 20;
+
+fn main() {
+    let mut args = args().skip(1);
+    let path = match args.next() {
+        Some(path) => path,
+        None => {
+            eprintln!("Usage: intcode-profile <program> [input...]");
+            exit(1);
+        }
+    };
+
+    let program = parse::load(&path).unwrap_or_else(|err| {
+        eprintln!("{}: {}", path, err);
+        exit(1);
+    });
+    let mut machine: Machine = Machine::new(program);
+    machine.enable_profiling();
+    let mut input = args.map(|arg| arg.parse().unwrap());
+    let mut outputs = Outputs::new(machine, move || input.next());
+
+    for res in &mut outputs {
+        match res {
+            Ok(val) => println!("{}", val),
+            Err(err) => eprintln!("{}", err),
+        }
+    }
+
+    let machine = outputs.into_machine();
+    let profile = machine.profile().unwrap();
+    print!("\n{}", profile.report(machine.memory(), HOT_SPOTS));
+}
@@ -1,9 +1,5 @@
-use crate::intcode::{interpret, parse_program};
+use crate::intcode::{parse_program, Machine, State};
 use async_std::stream::Stream;
-use async_std::sync::channel;
-use async_stream::stream;
-use futures::stream::StreamExt;
-use futures_util::pin_mut;
 use std::collections::HashSet;
 
 #[derive(Clone, Copy)]
@@ -48,29 +44,33 @@ impl PaintingRobot {
         }
     }
 
-    fn paint<'a, S: Stream<Item = i128> + Unpin + 'a>(
-        &'a mut self,
-        mut input: S,
-    ) -> impl Stream<Item = i128> + 'a {
-        stream!(
-            let mut pos = (0, 0);
-            let mut dir = Direction::Up;
-
-            loop {
-                yield if self.whites.contains(&pos) { 1 } else { 0 };
-
-                match input.next().await {
-                    None => break,
-                    Some(0) => { self.whites.remove(&pos); },
-                    Some(1) => { self.whites.insert(pos.clone()); },
-                    Some(c) => unreachable!("Invalid color code: {}", c),
-                };
-                self.visited.insert(pos.clone());
-
-                dir = dir.rotate(input.next().await.unwrap());
-                pos = dir.shift(pos);
+    fn paint(&mut self, mut brain: Machine) {
+        let mut pos = (0, 0);
+        let mut dir = Direction::Up;
+
+        loop {
+            match brain.run_until_event().unwrap() {
+                State::NeedsInput => {
+                    brain.input(if self.whites.contains(&pos) { 1 } else { 0 });
+                    continue;
+                }
+                State::Output(0) => {
+                    self.whites.remove(&pos);
+                }
+                State::Output(1) => {
+                    self.whites.insert(pos);
+                }
+                State::Output(c) => unreachable!("Invalid color code: {}", c),
+                _ => break,
+            };
+            self.visited.insert(pos);
+
+            match brain.run_until_event().unwrap() {
+                State::Output(turn) => dir = dir.rotate(turn),
+                state => unreachable!("Expected turn direction, got: {:?}", state),
             }
-        )
+            pos = dir.shift(pos);
+        }
     }
 }
 
@@ -78,15 +78,7 @@ impl PaintingRobot {
 pub async fn simplified<S: Stream<Item = String> + Unpin>(mut input: S) -> usize {
     let program = parse_program(&mut input).await;
     let mut robot = PaintingRobot::new();
-    let (send, recv) = channel(1);
-
-    let program_output = interpret(program, recv);
-    pin_mut!(program_output);
-    robot
-        .paint(program_output)
-        .for_each(|color| send.send(color))
-        .await;
-
+    robot.paint(Machine::new(program));
     robot.visited.len()
 }
 
@@ -111,14 +103,7 @@ pub async fn extended<S: Stream<Item = String> + Unpin>(mut input: S) -> () {
     let program = parse_program(&mut input).await;
     let mut robot = PaintingRobot::new();
     robot.whites.insert((0, 0));
-    let (send, recv) = channel(1);
-
-    let program_output = interpret(program, recv);
-    pin_mut!(program_output);
-    robot
-        .paint(program_output)
-        .for_each(|color| send.send(color))
-        .await;
+    robot.paint(Machine::new(program));
 
     println!("{}", draw(&robot.whites));
 }
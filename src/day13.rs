@@ -1,11 +1,10 @@
-use crate::intcode::{interpret, parse_program};
+use crate::intcode::{interpret, parse_program, Machine, State};
 use async_std::prelude::*;
 use async_std::stream::{self, Stream};
 use async_stream::stream;
 use futures_util::pin_mut;
 #[cfg(feature = "visual")]
 use pancurses::{endwin, initscr};
-use std::sync::Mutex;
 #[cfg(feature = "visual")]
 use std::thread::sleep;
 #[cfg(feature = "visual")]
@@ -29,34 +28,37 @@ pub async fn simplified<S: Stream<Item = String> + Unpin>(mut input: S) -> usize
     sprites.filter(|(_, _, id)| *id == 2).count().await
 }
 
+// Runs the arcade until it draws next sprite, moving the joystick towards
+// `ball_x` whenever the game asks for it. Returns `None` when game is over.
+fn next_sprite(
+    arcade: &mut Machine,
+    (paddle_x, ball_x): (i128, i128),
+) -> Option<(i128, i128, i128)> {
+    let mut output = || loop {
+        match arcade.run_until_event().unwrap() {
+            State::Output(v) => return Some(v),
+            State::NeedsInput => arcade.input((ball_x - paddle_x).signum()),
+            _ => return None,
+        }
+    };
+
+    Some((output()?, output()?, output()?))
+}
+
 #[allow(unused)]
 pub async fn extended<S: Stream<Item = String> + Unpin>(mut input: S) -> i128 {
     let mut program = parse_program(&mut input).await;
     program[0] = 2;
 
-    let status = Mutex::new((0, 0));
-    let control = stream::from_fn(|| {
-        let (paddle_x, ball_x) = *status.lock().unwrap();
-        if paddle_x < ball_x {
-            Some(1)
-        } else if paddle_x > ball_x {
-            Some(-1)
-        } else {
-            Some(0)
-        }
-    });
-
-    let output = interpret(program, control);
-    pin_mut!(output);
-    let sprites = sprites(output);
-    pin_mut!(sprites);
+    let mut arcade = Machine::new(program);
+    let mut status = (0, 0);
 
     #[cfg(feature = "visual")]
     let window = initscr();
     let mut score = 0;
 
     #[cfg(feature = "visual")]
-    while let Some((x, y, id)) = sprites.next().await {
+    while let Some((x, y, id)) = next_sprite(&mut arcade, status) {
         if x == -1 && y == 0 {
             window.mv(30, 0);
             window.addstr(format!("Score: {}", id));
@@ -74,10 +76,10 @@ pub async fn extended<S: Stream<Item = String> + Unpin>(mut input: S) -> i128 {
             window.addch(c);
 
             if c == '*' {
-                status.lock().unwrap().1 = x;
+                status.1 = x;
                 sleep(Duration::from_millis(10));
             } else if c == '_' {
-                status.lock().unwrap().0 = x;
+                status.0 = x;
             }
         }
 
@@ -85,13 +87,13 @@ pub async fn extended<S: Stream<Item = String> + Unpin>(mut input: S) -> i128 {
     }
 
     #[cfg(not(feature = "visual"))]
-    while let Some((x, y, id)) = sprites.next().await {
+    while let Some((x, y, id)) = next_sprite(&mut arcade, status) {
         if x == -1 && y == 0 {
             score = id;
         } else if id == 4 {
-            status.lock().unwrap().1 = x;
+            status.1 = x;
         } else if id == 3 {
-            status.lock().unwrap().0 = x;
+            status.0 = x;
         }
     }
 
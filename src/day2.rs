@@ -1,38 +1,13 @@
-use async_std::prelude::*;
-use async_std::stream::{from_iter, Stream};
-
-fn interpret(mut program: Vec<i64>) -> i64 {
-    let mut idx = 0;
-
-    while let Some(op) = program.get(idx) {
-        let res = match op {
-            1 => program[program[idx + 1] as usize] + program[program[idx + 2] as usize],
-            2 => program[program[idx + 1] as usize] * program[program[idx + 2] as usize],
-            99 => break,
-            _ => unreachable!(),
-        };
-        let res_idx = program[idx + 3] as usize;
-        program[res_idx] = res;
-
-        idx += 4
-    }
-
-    program[0]
-}
+use crate::intcode::{evaluate, parse, try_evaluate};
+use async_std::stream::Stream;
+use futures_util::pin_mut;
+use std::iter;
 
 async fn parse_program(input: impl Stream<Item = String>) -> Vec<i64> {
-    input
-        .map(|l| {
-            from_iter(
-                l.split(',')
-                    .map(str::parse::<i64>)
-                    .filter_map(Result::ok)
-                    .collect::<Vec<_>>(),
-            )
-        })
-        .flatten()
-        .collect()
+    pin_mut!(input);
+    parse::read(&mut input)
         .await
+        .unwrap_or_else(|err| panic!("Invalid program: {}", err))
 }
 
 #[allow(unused)]
@@ -42,7 +17,7 @@ pub async fn simplified(input: impl Stream<Item = String>) -> i64 {
     program[1] = 12;
     program[2] = 2;
 
-    interpret(program)
+    evaluate(program, iter::empty())
 }
 
 #[allow(unused)]
@@ -56,7 +31,9 @@ pub async fn extended(input: impl Stream<Item = String>) -> i64 {
             program[1] = noun;
             program[2] = verb;
 
-            if interpret(program) == 19690720 {
+            // Some combinations produce invalid programs, they are not the
+            // searched ones
+            if try_evaluate(program, iter::empty()) == Ok(19690720) {
                 return 100 * noun + verb;
             }
         }
@@ -67,17 +44,24 @@ pub async fn extended(input: impl Stream<Item = String>) -> i64 {
 
 #[cfg(test)]
 mod tests {
-    use super::interpret;
+    use crate::intcode::evaluate;
+    use std::iter;
 
     #[test]
     fn interpret_test() {
         assert_eq!(
             3500,
-            interpret(vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50])
+            evaluate::<i64>(
+                vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50],
+                iter::empty()
+            )
+        );
+        assert_eq!(2, evaluate::<i64>(vec![1, 0, 0, 0, 99], iter::empty()));
+        assert_eq!(2, evaluate::<i64>(vec![2, 3, 0, 3, 99], iter::empty()));
+        assert_eq!(2, evaluate::<i64>(vec![2, 4, 4, 5, 99, 0], iter::empty()));
+        assert_eq!(
+            30,
+            evaluate::<i64>(vec![1, 1, 1, 4, 99, 5, 6, 0, 99], iter::empty())
         );
-        assert_eq!(2, interpret(vec![1, 0, 0, 0, 99]));
-        assert_eq!(2, interpret(vec![2, 3, 0, 3, 99]));
-        assert_eq!(2, interpret(vec![2, 4, 4, 5, 99, 0]));
-        assert_eq!(30, interpret(vec![1, 1, 1, 4, 99, 5, 6, 0, 99]));
     }
 }
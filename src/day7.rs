@@ -8,18 +8,7 @@ use futures::future::join_all;
 use futures::stream::StreamExt;
 use permute::permutations_of;
 
-use crate::intcode::interpret;
-
-async fn parse_program<S: Stream<Item = String> + Unpin>(input: &mut S) -> Vec<i128> {
-    input
-        .next()
-        .await
-        .unwrap()
-        .split(',')
-        .map(str::parse::<i128>)
-        .filter_map(Result::ok)
-        .collect::<Vec<_>>()
-}
+use crate::intcode::{interpret, parse_program};
 
 #[allow(unused)]
 pub async fn simplified<S: Stream<Item = String> + Unpin>(mut input: S) -> i128 {
@@ -1,19 +1,8 @@
-use crate::intcode::interpret;
+use crate::intcode::{interpret, parse_program};
 use async_std;
 use async_std::stream::{self, Stream};
 use futures::stream::StreamExt;
 
-async fn parse_program<S: Stream<Item = String> + Unpin>(input: &mut S) -> Vec<i128> {
-    input
-        .next()
-        .await
-        .unwrap()
-        .split(',')
-        .map(str::parse::<i128>)
-        .filter_map(Result::ok)
-        .collect::<Vec<_>>()
-}
-
 #[allow(unused)]
 pub async fn simplified<S: Stream<Item = String> + Unpin>(mut input: S) -> Vec<i128> {
     let program = parse_program(&mut input).await;
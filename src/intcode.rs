@@ -1,15 +1,156 @@
-use async_std;
 use async_std::prelude::*;
 use async_std::stream::Stream;
 use async_stream::stream;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
 
-struct Machine {
-    memory: Vec<i128>,
+pub mod ascii;
+pub mod asm;
+pub mod cfg;
+pub mod compile;
+pub mod coverage;
+pub mod debugger;
+pub mod disasm;
+pub mod history;
+pub mod limits;
+pub mod memory;
+pub mod network;
+pub mod parse;
+pub mod profile;
+pub mod selfmod;
+pub mod session;
+pub mod snapshot;
+pub mod trace;
+pub mod transcript;
+pub mod watch;
+pub mod word;
+
+use history::{Entry, History};
+use limits::{Limits, Watchdog};
+use memory::{Cells, Memory};
+use trace::{Event, Tracer};
+use transcript::Io;
+use watch::{Access, Callback, Watchpoints};
+use word::{Overflow, Word};
+
+/// Reason of Intcode program failure
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind {
+    /// Opcode which doesn't name any known instruction
+    InvalidOpcode,
+    /// Argument mode other than position, immediate or relative
+    InvalidMode(i128),
+    /// Instruction tried to store its result in immediate argument
+    WriteToImmediate,
+    /// Memory access or jump to negative address
+    NegativeAddress(i128),
+    /// Address or relative base too large to be represented on this platform
+    AddressOutOfRange(i128),
+    /// Program tried to read, but input stream is already exhausted
+    InputExhausted,
+    /// Machine already executed maximum allowed number of instructions
+    InstructionLimit(u64),
+    /// Machine exceeded its wall-clock execution time
+    Timeout(Duration),
+    /// Machine revisited its previous state without consuming any input
+    InfiniteLoop,
+    /// Arithmetic result doesn't fit in machine word
+    Overflow,
+}
+
+/// Intcode program failure, with context of instruction which caused it.
+/// Values which don't fit in `i128` are clamped to its bounds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntcodeError {
+    /// Address of failing instruction
+    pub pc: usize,
+    /// Raw opcode of failing instruction (including argument modes)
+    pub opcode: i128,
+    /// Index of failing argument, if failure is related to any
+    pub argument: Option<usize>,
+    pub kind: ErrorKind,
+}
+
+impl fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: [{}] ", self.pc, self.opcode)?;
+        if let Some(arg) = self.argument {
+            write!(f, "argument {}: ", arg)?;
+        }
+
+        match &self.kind {
+            ErrorKind::InvalidOpcode => write!(f, "invalid opcode {}", self.opcode % 100),
+            ErrorKind::InvalidMode(m) => write!(f, "invalid argument mode {}", m),
+            ErrorKind::WriteToImmediate => write!(f, "trying to output to immediate argument"),
+            ErrorKind::NegativeAddress(a) => write!(f, "negative address {}", a),
+            ErrorKind::AddressOutOfRange(a) => write!(f, "address {} out of range", a),
+            ErrorKind::InputExhausted => write!(f, "input exhausted"),
+            ErrorKind::InstructionLimit(max) => write!(f, "limit of {} instructions reached", max),
+            ErrorKind::Timeout(timeout) => write!(f, "timeout of {:?} exceeded", timeout),
+            ErrorKind::InfiniteLoop => write!(f, "infinite loop detected"),
+            ErrorKind::Overflow => write!(f, "arithmetic overflow"),
+        }
+    }
+}
+
+impl std::error::Error for IntcodeError {}
+
+// Failure detected while performing an instruction, not yet bound to its
+// pc and opcode: (argument_idx, kind)
+type Fault = (Option<usize>, ErrorKind);
+
+/// Machine state after executing instructions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum State<W = i128> {
+    /// Instruction executed, machine may continue
+    Running,
+    /// Program reached its termination opcode
+    Halted,
+    /// Program wants to read, but no input is buffered; `pc` stays on the
+    /// read instruction until input is provided
+    NeedsInput,
+    /// Program produced output value
+    Output(W),
+    /// Instruction executed, and watchpoint on given address asked to stop
+    /// after it. When stopping instruction produced output, this state is
+    /// reported on next step, without executing anything.
+    Watchpoint(usize),
+}
+
+/// Intcode virtual machine which can be driven synchronously, instruction
+/// by instruction. Machine is generic over type of its memory cells.
+pub struct Machine<W = i128> {
+    id: usize,
+    memory: Memory<W>,
+    pc: usize,
     relative_base: isize,
+    input: VecDeque<W>,
+    overflow: Overflow,
+    tracer: Option<Box<dyn Tracer<W>>>,
+    executed: u64,
+    watchdog: Option<Watchdog>,
+    // Decoded instructions of program image by their address, `None` if not
+    // decoded yet or invalidated by write. Whole cache is `None` if disabled.
+    decoded: Option<Vec<Option<Instruction<W>>>>,
+    self_modification: Option<selfmod::Tracker<W>>,
+    profile: Option<profile::Profile>,
+    coverage: Option<coverage::Coverage>,
+    watchpoints: Watchpoints<W>,
+    history: Option<History<W>>,
+    transcript: Option<transcript::Transcript<W>>,
+    // Termination opcode at `pc` was already reported
+    halted: bool,
 }
 
-#[derive(Debug)]
-enum Op {
+type Instruction<W> = (Op, [Argument<W>; 3]);
+
+/// Intcode instruction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
     Add,
     Mul,
     Read,
@@ -22,8 +163,8 @@ enum Op {
 }
 
 impl Op {
-    fn new(code: i128) -> Self {
-        match code % 100 {
+    fn new(code: i128) -> Option<Self> {
+        let op = match code % 100 {
             1 => Self::Add,
             2 => Self::Mul,
             3 => Self::Read,
@@ -33,233 +174,1113 @@ impl Op {
             7 => Self::Less,
             8 => Self::Equal,
             9 => Self::MoveBase,
-            c => panic!("Invalid opcode: {}", c),
-        }
+            _ => return None,
+        };
+
+        Some(op)
     }
 
     // Returns:
     // (new_pc, output_val)
     // For non jump instructions returned `new_pc` should be `None`
     // For non output instructions returned `output_val` should be `None`
-    async fn perform<S: Stream<Item = i128> + Unpin>(
+    fn perform<W: Word>(
         &self,
-        args: &[Argument],
-        machine: &mut Machine,
-        input: &mut S,
-    ) -> (Option<usize>, Option<i128>) {
+        args: &[Argument<W>],
+        machine: &mut Machine<W>,
+    ) -> Result<(Option<usize>, Option<W>), Fault> {
+        let get = |machine: &Machine<W>, idx: usize| {
+            args[idx].get(machine).map_err(|kind| (Some(idx), kind))
+        };
+        let set = |machine: &mut Machine<W>, idx: usize, val| {
+            args[idx]
+                .set(machine, val)
+                .map_err(|kind| (Some(idx), kind))
+        };
+        let jump =
+            |idx: usize, target: W| address(&target).map(Some).map_err(|kind| (Some(idx), kind));
+
         match self {
             Self::Add => {
-                let arg1 = args[0].get(machine);
-                let arg2 = args[1].get(machine);
-                #[cfg(feature = "debug")]
-                println!(
-                    "ADD   {:5?}[{:5}]  {:5?}[{:5}]  {:5?}",
-                    args[0], arg1, args[1], arg2, args[2]
-                );
-                args[2].set(machine, arg1 + arg2);
-                (None, None)
+                let arg1 = get(machine, 0)?;
+                let arg2 = get(machine, 1)?;
+                let res = arg1
+                    .add(&arg2, machine.overflow)
+                    .ok_or((None, ErrorKind::Overflow))?;
+                set(machine, 2, res)?;
+                Ok((None, None))
             }
             Self::Mul => {
-                let arg1 = args[0].get(machine);
-                let arg2 = args[1].get(machine);
-                #[cfg(feature = "debug")]
-                println!(
-                    "MUL   {:5?}[{:5}]  {:5?}[{:5}]  {:5?}",
-                    args[0], arg1, args[1], arg2, args[2]
-                );
-                args[2].set(machine, arg1 * arg2);
-                (None, None)
+                let arg1 = get(machine, 0)?;
+                let arg2 = get(machine, 1)?;
+                let res = arg1
+                    .mul(&arg2, machine.overflow)
+                    .ok_or((None, ErrorKind::Overflow))?;
+                set(machine, 2, res)?;
+                Ok((None, None))
             }
             Self::Read => {
-                let readed = input.next().await.unwrap();
-                #[cfg(feature = "debug")]
-                println!("READ  [{:5}] {:5?}", readed, args[0]);
-                args[0].set(machine, readed);
-                (None, None)
+                // Input is kept if it cannot be stored
+                let addr = args[0].address(machine).map_err(|kind| (Some(0), kind))?;
+                let readed = machine
+                    .input
+                    .pop_front()
+                    .ok_or((None, ErrorKind::InputExhausted))?;
+                machine.store(addr, readed, Some(machine.pc));
+                Ok((None, None))
             }
             Self::Write => {
-                let writting = args[0].get(machine);
-                #[cfg(feature = "debug")]
-                println!("WRT   {:5?}[{:5}]", args[0], writting);
-                (None, Some(writting))
+                let writting = get(machine, 0)?;
+                Ok((None, Some(writting)))
             }
             Self::JmpT => {
-                let arg1 = args[0].get(machine);
-                let arg2 = args[1].get(machine);
-                #[cfg(feature = "debug")]
-                println!(
-                    "JMPT  {:5?}[{:5}]  {:5?}[{:5}]",
-                    args[0], arg1, args[1], arg2
-                );
-                let new_pc = if arg1 != 0 { Some(arg2 as usize) } else { None };
-                (new_pc, None)
+                let arg1 = get(machine, 0)?;
+                let arg2 = get(machine, 1)?;
+                let new_pc = if !arg1.is_zero() {
+                    jump(1, arg2)?
+                } else {
+                    None
+                };
+                Ok((new_pc, None))
             }
             Self::JmpF => {
-                let arg1 = args[0].get(machine);
-                let arg2 = args[1].get(machine);
-                #[cfg(feature = "debug")]
-                println!(
-                    "JMPF  {:5?}[{:5}]  {:5?}[{:5}]",
-                    args[0], arg1, args[1], arg2
-                );
-                let new_pc = if arg1 == 0 { Some(arg2 as usize) } else { None };
-                (new_pc, None)
+                let arg1 = get(machine, 0)?;
+                let arg2 = get(machine, 1)?;
+                let new_pc = if arg1.is_zero() { jump(1, arg2)? } else { None };
+                Ok((new_pc, None))
             }
             Self::Less => {
-                let arg1 = args[0].get(machine);
-                let arg2 = args[1].get(machine);
-                #[cfg(feature = "debug")]
-                println!(
-                    "LESS  {:5?}[{:5}]  {:5?}[{:5}]  {:5?}",
-                    args[0], arg1, args[1], arg2, args[2]
-                );
-                if arg1 < arg2 {
-                    args[2].set(machine, 1);
-                } else {
-                    args[2].set(machine, 0);
-                }
-                (None, None)
+                let arg1 = get(machine, 0)?;
+                let arg2 = get(machine, 1)?;
+                set(machine, 2, W::from_i64((arg1 < arg2) as i64))?;
+                Ok((None, None))
             }
             Self::Equal => {
-                let arg1 = args[0].get(machine);
-                let arg2 = args[1].get(machine);
-                #[cfg(feature = "debug")]
-                println!(
-                    "EQ    {:5?}[{:5}]  {:5?}[{:5}]  {:5?}",
-                    args[0], arg1, args[1], arg2, args[2]
-                );
-                if arg1 == arg2 {
-                    args[2].set(machine, 1);
-                } else {
-                    args[2].set(machine, 0);
-                }
-                (None, None)
+                let arg1 = get(machine, 0)?;
+                let arg2 = get(machine, 1)?;
+                set(machine, 2, W::from_i64((arg1 == arg2) as i64))?;
+                Ok((None, None))
             }
             Self::MoveBase => {
-                let arg = args[0].get(machine);
-                #[cfg(feature = "debug")]
-                println!("MVB   {:5?}[{:5}]", args[0], arg);
-                machine.relative_base += arg as isize;
-                (None, None)
+                let arg = get(machine, 0)?;
+                machine.relative_base = arg
+                    .to_i128()
+                    .and_then(|arg| isize::try_from(arg).ok())
+                    .and_then(|arg| machine.relative_base.checked_add(arg))
+                    .ok_or((Some(0), ErrorKind::AddressOutOfRange(arg.clamp_i128())))?;
+                Ok((None, None))
             }
         }
     }
 
-    fn args(&self) -> usize {
+    /// Opcode of instruction, without argument modes
+    pub fn code(&self) -> i128 {
+        match self {
+            Self::Add => 1,
+            Self::Mul => 2,
+            Self::Read => 3,
+            Self::Write => 4,
+            Self::JmpT => 5,
+            Self::JmpF => 6,
+            Self::Less => 7,
+            Self::Equal => 8,
+            Self::MoveBase => 9,
+        }
+    }
+
+    /// Number of arguments taken by instruction
+    pub fn args(&self) -> usize {
         match self {
             Self::Add | Self::Mul | Self::Less | Self::Equal => 3,
             Self::Read | Self::Write | Self::MoveBase => 1,
             Self::JmpT | Self::JmpF => 2,
         }
     }
+
+    /// Index of argument to which instruction stores its result
+    pub fn output_arg(&self) -> Option<usize> {
+        match self {
+            Self::Add | Self::Mul | Self::Less | Self::Equal => Some(2),
+            Self::Read => Some(0),
+            Self::Write | Self::JmpT | Self::JmpF | Self::MoveBase => None,
+        }
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Self::Add => "ADD",
+            Self::Mul => "MUL",
+            Self::Read => "READ",
+            Self::Write => "WRT",
+            Self::JmpT => "JMPT",
+            Self::JmpF => "JMPF",
+            Self::Less => "LESS",
+            Self::Equal => "EQ",
+            Self::MoveBase => "MVB",
+        }
+    }
 }
 
-#[derive(Clone, Copy, Debug)]
-enum Argument {
-    Imm(i128),
+/// Instruction argument, decoded according to its mode
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Argument<W = i128> {
+    Imm(W),
     Pos(usize),
     Rel(isize),
 }
 
-impl Argument {
-    fn get(&self, machine: &mut Machine) -> i128 {
-        let idx = match self {
-            Self::Imm(v) => return *v,
-            Self::Pos(a) => *a,
-            Self::Rel(r) => (machine.relative_base + *r) as usize,
-        };
-
-        if idx >= machine.memory.len() {
-            0
-        } else {
-            machine.memory[idx]
+impl<W: Word> Argument<W> {
+    fn address(&self, machine: &Machine<W>) -> Result<usize, ErrorKind> {
+        match self {
+            Self::Imm(_) => Err(ErrorKind::WriteToImmediate),
+            Self::Pos(a) => Ok(*a),
+            Self::Rel(r) => address(&(machine.relative_base as i128 + *r as i128)),
         }
     }
 
-    fn set(&self, machine: &mut Machine, val: i128) {
+    fn get(&self, machine: &Machine<W>) -> Result<W, ErrorKind> {
         let idx = match self {
-            Self::Imm(_) => panic!("Trying to output to immediate argument"),
-            Self::Pos(a) => *a,
-            Self::Rel(r) => (machine.relative_base + *r) as usize,
+            Self::Imm(v) => return Ok(v.clone()),
+            _ => self.address(machine)?,
         };
 
-        if idx >= machine.memory.len() {
-            machine.memory.resize(idx + 1, 0);
-        }
+        Ok(machine.peek(idx))
+    }
 
-        machine.memory[idx] = val;
+    fn set(&self, machine: &mut Machine<W>, val: W) -> Result<(), ErrorKind> {
+        let idx = self.address(machine)?;
+        machine.store(idx, val, Some(machine.pc));
+        Ok(())
     }
 }
 
-// Returns (output_val, new_pc)
-// For termination opcode, returned value should be `None`
-// For non output opcodes, returned `output_val` should be None
-async fn handle_opcode<S: Stream<Item = i128> + Unpin>(
-    pc: usize,
-    machine: &mut Machine,
-    input: &mut S,
-) -> Option<(Option<i128>, usize)> {
-    let mut opcode = machine.memory[pc];
-    #[cfg(feature = "debug")]
-    print!("{:4}: [{:5}] ", pc, opcode);
-
-    if opcode == 99 {
-        #[cfg(feature = "debug")]
-        println!("EXIT");
-        return None;
+// Converts value to memory address
+fn address<W: Word>(value: &W) -> Result<usize, ErrorKind> {
+    let clamped = value.clamp_i128();
+    if clamped < 0 {
+        Err(ErrorKind::NegativeAddress(clamped))
+    } else {
+        value
+            .to_i128()
+            .and_then(|v| usize::try_from(v).ok())
+            .ok_or(ErrorKind::AddressOutOfRange(clamped))
     }
+}
 
-    let op = Op::new(opcode);
-    opcode /= 100;
+// Decodes instruction at `pc`. Only first `op.args()` arguments are
+// meaningful, rest of them are `Imm(0)`. Termination opcode is not decoded
+// here and results in `InvalidOpcode`.
+fn decode<W: Word>(
+    memory: &(impl Cells<W> + ?Sized),
+    pc: usize,
+) -> Result<(Op, [Argument<W>; 3]), Fault> {
+    let raw = memory
+        .cell(pc)
+        .to_i128()
+        .ok_or((None, ErrorKind::InvalidOpcode))?;
+    let op = Op::new(raw).ok_or((None, ErrorKind::InvalidOpcode))?;
+    let mut opcode = raw / 100;
 
-    let mut args = [Argument::Imm(0); 3];
+    let zero = || Argument::Imm(W::from_i64(0));
+    let mut args = [zero(), zero(), zero()];
     for (i, arg) in (0..op.args()).zip(args.iter_mut()) {
-        let v = machine.memory[pc + i + 1];
+        let v = pc.checked_add(i + 1).map(|addr| memory.cell(addr)).ok_or((
+            Some(i),
+            ErrorKind::AddressOutOfRange(pc as i128 + i as i128 + 1),
+        ))?;
 
         *arg = match opcode % 10 {
-            0 => Argument::Pos(v as usize),
+            0 => Argument::Pos(address(&v).map_err(|kind| (Some(i), kind))?),
             1 => Argument::Imm(v),
-            2 => Argument::Rel(v as isize),
-            m => panic!("Invalid argument mode: {}", m),
+            2 => Argument::Rel(
+                v.to_i128()
+                    .and_then(|r| isize::try_from(r).ok())
+                    .ok_or((Some(i), ErrorKind::AddressOutOfRange(v.clamp_i128())))?,
+            ),
+            m => return Err((Some(i), ErrorKind::InvalidMode(m))),
         };
 
         opcode /= 10;
     }
 
-    let (new_pc, output_val) = op.perform(&args, machine, input).await;
-    let new_pc = new_pc.unwrap_or_else(|| pc + op.args() + 1);
-    Some((output_val, new_pc))
+    Ok((op, args))
 }
 
-pub fn interpret<S: Stream<Item = i128> + Unpin>(
-    program: Vec<i128>,
+// Id of next created machine
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+impl<W: Word> Machine<W> {
+    pub fn new(program: Vec<W>) -> Self {
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            memory: Memory::new(program),
+            pc: 0,
+            relative_base: 0,
+            input: VecDeque::new(),
+            overflow: Overflow::default(),
+            tracer: trace::from_env(),
+            executed: 0,
+            watchdog: None,
+            decoded: Some(vec![]),
+            self_modification: None,
+            profile: None,
+            coverage: None,
+            watchpoints: Watchpoints::new(),
+            history: None,
+            transcript: None,
+            halted: false,
+        }
+    }
+
+    /// Enables or disables caching of decoded instructions. Cache is enabled
+    /// by default, disabling it may be beneficial only for programs
+    /// constantly rewriting their own code.
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.decoded = if enabled { Some(vec![]) } else { None };
+    }
+
+    /// Sets execution limits. Exceeding limit is reported as an error, but
+    /// machine is not broken by it - it may be resumed after limits are
+    /// changed.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.watchdog = Some(Watchdog::new(limits));
+    }
+
+    /// Selects behavior of arithmetic overflow, by default it is an error
+    pub fn set_overflow(&mut self, overflow: Overflow) {
+        self.overflow = overflow;
+    }
+
+    /// Identifier of machine, unique within process, distinguishing traces
+    /// of different machines
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Number of instructions executed so far
+    pub fn executed(&self) -> u64 {
+        self.executed
+    }
+
+    /// Replaces tracer receiving executed instructions. By default tracer is
+    /// selected by `trace::TRACE_VAR` environment variable.
+    pub fn set_tracer(&mut self, tracer: Option<Box<dyn Tracer<W>>>) {
+        self.tracer = tracer;
+    }
+
+    /// Buffers value to be consumed by program reads
+    pub fn input(&mut self, val: W) {
+        self.input.push_back(val);
+    }
+
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn relative_base(&self) -> isize {
+        self.relative_base
+    }
+
+    pub fn memory(&self) -> &Memory<W> {
+        &self.memory
+    }
+
+    /// Reads memory cell, cells never written are always 0
+    pub fn peek(&self, addr: usize) -> W {
+        self.memory.get(addr)
+    }
+
+    /// Starts recording writes into code, either executed or found by static
+    /// analysis of current memory. Recording is expensive, so it is meant
+    /// for analysis only.
+    pub fn analyze_self_modification(&mut self) {
+        self.self_modification = Some(selfmod::Tracker::new(&self.memory));
+    }
+
+    /// Writes into code recorded so far, `None` if analysis is not enabled
+    pub fn self_modification(&self) -> Option<&selfmod::Report<W>> {
+        self.self_modification
+            .as_ref()
+            .map(selfmod::Tracker::report)
+    }
+
+    /// Starts collecting execution statistics, discarding previously
+    /// collected ones
+    pub fn enable_profiling(&mut self) {
+        self.profile = Some(profile::Profile::new(self.memory.allocated()));
+    }
+
+    /// Statistics collected so far, `None` if profiling is not enabled
+    pub fn profile(&self) -> Option<&profile::Profile> {
+        self.profile.as_ref()
+    }
+
+    /// Starts tracking addresses executed, read and written by program,
+    /// discarding previously tracked ones
+    pub fn enable_coverage(&mut self) {
+        self.coverage = Some(coverage::Coverage::default());
+    }
+
+    /// Coverage tracked so far, `None` if tracking is not enabled
+    pub fn coverage(&self) -> Option<&coverage::Coverage> {
+        self.coverage.as_ref()
+    }
+
+    /// Registers watchpoint calling `callback` on every `access` to
+    /// addresses in `range`. Returns id of watchpoint.
+    pub fn watch(&mut self, range: Range<usize>, access: Access, callback: Callback<W>) -> usize {
+        self.watchpoints.add(range, access, callback)
+    }
+
+    /// Removes watchpoint, returns `false` if there was no such watchpoint
+    pub fn unwatch(&mut self, id: usize) -> bool {
+        self.watchpoints.remove(id)
+    }
+
+    /// Starts recording undo log of executed instructions, so machine may
+    /// be moved back to any of `limit` most recent states. Previous log is
+    /// discarded.
+    pub fn record_history(&mut self, limit: usize) {
+        self.history = Some(History::new(limit));
+    }
+
+    /// Number of instructions which may be undone
+    pub fn history_len(&self) -> usize {
+        self.history.as_ref().map_or(0, History::len)
+    }
+
+    /// Undoes last executed instruction. Returns `false` if it is not
+    /// recorded in history.
+    ///
+    /// Recorded transcript is rewound together with machine. Profile,
+    /// coverage and self-modification analysis cannot be rewound, so undo
+    /// disables them.
+    pub fn step_back(&mut self) -> bool {
+        let entry = match self.history.as_mut().and_then(History::pop) {
+            Some(entry) => entry,
+            None => return false,
+        };
+
+        // Most recent write is undone first
+        for (addr, val) in entry.pokes.into_iter().rev().chain(entry.write) {
+            self.memory.set(addr, val);
+            self.invalidate(addr);
+        }
+        if let Some(val) = entry.input {
+            self.input.push_front(val);
+        }
+        self.pc = entry.pc;
+        self.relative_base = entry.relative_base;
+        self.executed -= 1;
+        self.watchpoints.pending = None;
+
+        if let Some(transcript) = &mut self.transcript {
+            transcript.rewind(self.executed);
+        }
+        self.profile = None;
+        self.coverage = None;
+        self.self_modification = None;
+        true
+    }
+
+    /// Moves machine back to state after `executed` instructions. Returns
+    /// `false` and leaves machine unchanged if such state is not recorded in
+    /// history.
+    pub fn travel_to(&mut self, executed: u64) -> bool {
+        let back = match self.executed.checked_sub(executed) {
+            Some(back) if back <= self.history_len() as u64 => back,
+            _ => return false,
+        };
+
+        for _ in 0..back {
+            self.step_back();
+        }
+        true
+    }
+
+    /// Starts recording values read and written by program, discarding
+    /// previously recorded ones
+    pub fn record_transcript(&mut self) {
+        self.transcript = Some(transcript::Transcript::default());
+    }
+
+    /// Values recorded so far, `None` if recording is not enabled
+    pub fn transcript(&self) -> Option<&transcript::Transcript<W>> {
+        self.transcript.as_ref()
+    }
+
+    /// Overwrites memory cell. When history is recorded, it is undone
+    /// together with previous instruction.
+    pub fn poke(&mut self, addr: usize, val: W) {
+        if let Some(history) = &mut self.history {
+            history.poke(addr, self.memory.get(addr));
+        }
+        self.store(addr, val, None);
+    }
+
+    // Writes memory cell, `pc` is address of writing instruction
+    fn store(&mut self, addr: usize, val: W, pc: Option<usize>) {
+        if let Some(tracker) = &mut self.self_modification {
+            tracker.write(pc, self.executed, addr, &self.memory[addr], &val);
+        }
+
+        if let (Some(coverage), Some(_)) = (&mut self.coverage, pc) {
+            coverage.record_write(addr);
+        }
+
+        self.memory.set(addr, val);
+        self.invalidate(addr);
+    }
+
+    // Every instruction overlapping written cell has to be decoded again
+    fn invalidate(&mut self, addr: usize) {
+        if let Some(decoded) = &mut self.decoded {
+            let start = addr.saturating_sub(3).min(decoded.len());
+            let end = addr.saturating_add(1).min(decoded.len());
+            decoded[start..end]
+                .iter_mut()
+                .for_each(|instr| *instr = None);
+        }
+    }
+
+    fn decode(&mut self, pc: usize) -> Result<Instruction<W>, Fault> {
+        let decoded = match &mut self.decoded {
+            Some(decoded) => decoded,
+            None => return decode(&self.memory, pc),
+        };
+
+        if let Some(Some(instr)) = decoded.get(pc) {
+            return Ok(instr.clone());
+        }
+
+        let instr = decode(&self.memory, pc)?;
+        // Only program image is cached, code is hardly ever executed beyond it
+        if pc < self.memory.image().len() {
+            if pc >= decoded.len() {
+                decoded.resize(self.memory.image().len(), None);
+            }
+            decoded[pc] = Some(instr.clone());
+        }
+
+        Ok(instr)
+    }
+
+    /// Number of values buffered for program reads
+    pub fn pending_input(&self) -> usize {
+        self.input.len()
+    }
+
+    fn error(&self, argument: Option<usize>, kind: ErrorKind) -> IntcodeError {
+        IntcodeError {
+            pc: self.pc,
+            opcode: self.memory.get(self.pc).clamp_i128(),
+            argument,
+            kind,
+        }
+    }
+
+    /// Executes single instruction
+    pub fn step(&mut self) -> Result<State<W>, IntcodeError> {
+        if let Some(addr) = self.watchpoints.pending.take() {
+            return Ok(State::Watchpoint(addr));
+        }
+
+        let pc = self.pc;
+        let raw = self.memory.get(pc).clamp_i128();
+
+        if raw == 99 {
+            // Halting again is not another executed instruction
+            if self.halted {
+                return Ok(State::Halted);
+            }
+            self.halted = true;
+
+            if let Some(profile) = &mut self.profile {
+                profile.record_exit(pc);
+            }
+            if let Some(coverage) = &mut self.coverage {
+                coverage.record_execute(pc, 1);
+            }
+            if let Some(tracer) = &mut self.tracer {
+                tracer.trace(&Event {
+                    machine: self.id,
+                    pc,
+                    opcode: raw,
+                    op: None,
+                    args: vec![],
+                    values: vec![],
+                    write: None,
+                });
+            }
+            return Ok(State::Halted);
+        }
+        self.halted = false;
+
+        let (op, args) = self
+            .decode(pc)
+            .map_err(|(argument, kind)| self.error(argument, kind))?;
+        let args = &args[..op.args()];
+
+        // Instruction ending at the last address has no successor, it may
+        // only jump away
+        let next = pc.checked_add(op.args() + 1);
+        let no_successor = ErrorKind::AddressOutOfRange(pc as i128 + op.args() as i128 + 1);
+        match (next, op) {
+            (None, Op::JmpT) | (None, Op::JmpF) | (Some(_), _) => (),
+            (None, _) => return Err(self.error(None, no_successor)),
+        }
+
+        if let Op::Read = op {
+            if self.input.is_empty() {
+                return Ok(State::NeedsInput);
+            }
+        }
+
+        if let Some(mut watchdog) = self.watchdog.take() {
+            let checked = watchdog.check(self);
+            self.watchdog = Some(watchdog);
+            checked.map_err(|kind| self.error(None, kind))?;
+        }
+
+        // Values have to be resolved before instruction is performed, as it
+        // may overwrite them
+        let traced = self.tracer.as_ref().map(|_| {
+            let values: Vec<_> = (0..args.len())
+                .filter(|idx| op.output_arg() != Some(*idx))
+                .filter_map(|idx| args[idx].get(self).ok())
+                .collect();
+            let write = op.output_arg().and_then(|idx| args[idx].address(self).ok());
+            (values, write)
+        });
+
+        if let Some(tracker) = &mut self.self_modification {
+            tracker.execute(pc, op.args() + 1);
+        }
+
+        if let Some(mut coverage) = self.coverage.take() {
+            coverage.record_execute(pc, op.args() + 1);
+            for idx in (0..args.len()).filter(|idx| op.output_arg() != Some(*idx)) {
+                // Immediate arguments have no address
+                if let Ok(addr) = args[idx].address(self) {
+                    coverage.record_read(addr);
+                }
+            }
+            self.coverage = Some(coverage);
+        }
+
+        let undo = self.history.as_ref().map(|_| Entry {
+            pc,
+            relative_base: self.relative_base,
+            write: op
+                .output_arg()
+                .and_then(|idx| args[idx].address(self).ok())
+                .map(|addr| (addr, self.peek(addr))),
+            input: self.input.front().cloned().filter(|_| op == Op::Read),
+            pokes: vec![],
+        });
+
+        let read = match (&self.transcript, op) {
+            (Some(_), Op::Read) => self.input.front().cloned(),
+            _ => None,
+        };
+
+        let watched = if self.watchpoints.is_empty() {
+            None
+        } else {
+            let reads: Vec<_> = (0..args.len())
+                .filter(|idx| op.output_arg() != Some(*idx))
+                .filter_map(|idx| args[idx].address(self).ok())
+                .map(|addr| (addr, self.peek(addr)))
+                .collect();
+            let write = op
+                .output_arg()
+                .and_then(|idx| args[idx].address(self).ok())
+                .map(|addr| (addr, self.peek(addr)));
+            Some((reads, write))
+        };
+
+        let (new_pc, output_val) = op
+            .perform(args, self)
+            .map_err(|(argument, kind)| self.error(argument, kind))?;
+        self.pc = new_pc
+            .or(next)
+            .ok_or_else(|| self.error(None, no_successor))?;
+
+        if let Some(transcript) = &mut self.transcript {
+            if let Some(val) = read {
+                transcript.events.push(Io::Input(self.executed, val));
+            }
+            if let Some(val) = &output_val {
+                transcript
+                    .events
+                    .push(Io::Output(self.executed, val.clone()));
+            }
+        }
+
+        self.executed += 1;
+
+        // Input is consumed only by successfully performed instruction
+        if let (Some(watchdog), Op::Read) = (&mut self.watchdog, op) {
+            watchdog.input_consumed();
+        }
+
+        if let (Some(history), Some(entry)) = (&mut self.history, undo) {
+            history.push(entry);
+        }
+
+        if let Some(profile) = &mut self.profile {
+            profile.record(pc, op, self.pc, self.memory.allocated());
+        }
+
+        if let Some((values, write)) = traced {
+            let event = Event {
+                machine: self.id,
+                pc,
+                opcode: raw,
+                op: Some(op),
+                args: args.to_vec(),
+                values,
+                write: write.map(|addr| (addr, self.peek(addr))),
+            };
+            if let Some(tracer) = &mut self.tracer {
+                tracer.trace(&event);
+            }
+        }
+
+        if let Some((reads, write)) = watched {
+            let write = write.map(|(addr, old)| (addr, old, self.peek(addr)));
+            if let Some(addr) = self.watchpoints.check(pc, &reads, write) {
+                match output_val {
+                    Some(val) => {
+                        self.watchpoints.pending = Some(addr);
+                        return Ok(State::Output(val));
+                    }
+                    None => return Ok(State::Watchpoint(addr)),
+                }
+            }
+        }
+
+        Ok(output_val.map_or(State::Running, State::Output))
+    }
+
+    /// Executes instructions until machine halts, needs input or produces
+    /// output
+    pub fn run_until_event(&mut self) -> Result<State<W>, IntcodeError> {
+        loop {
+            match self.step()? {
+                State::Running => (),
+                state => return Ok(state),
+            }
+        }
+    }
+}
+
+/// Synchronous run of program, iterating over its outputs. Whenever program
+/// reads, but no input is buffered in machine, `input` closure is called for
+/// next value.
+///
+/// Iteration finishes when program halts or is stopped by watchpoint.
+/// If program fails, the error is the last item. `ErrorKind::InputExhausted`
+/// is reported when `input` returns `None` - machine stays on its read
+/// instruction then, so iteration may be resumed with `resume` after
+/// buffering more input with `machine_mut`.
+pub struct Outputs<W, F> {
+    machine: Machine<W>,
+    input: F,
+    finished: bool,
+}
+
+impl<W: Word, F: FnMut() -> Option<W>> Outputs<W, F> {
+    pub fn new(machine: Machine<W>, input: F) -> Self {
+        Self {
+            machine,
+            input,
+            finished: false,
+        }
+    }
+
+    pub fn machine(&self) -> &Machine<W> {
+        &self.machine
+    }
+
+    pub fn machine_mut(&mut self) -> &mut Machine<W> {
+        &mut self.machine
+    }
+
+    pub fn into_machine(self) -> Machine<W> {
+        self.machine
+    }
+
+    /// Continues iteration finished by an error
+    pub fn resume(&mut self) {
+        self.finished = false;
+    }
+}
+
+impl<W: Word, F: FnMut() -> Option<W>> Iterator for Outputs<W, F> {
+    type Item = Result<W, IntcodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        loop {
+            match self.machine.run_until_event() {
+                Ok(State::Output(val)) => return Some(Ok(val)),
+                Ok(State::NeedsInput) => match (self.input)() {
+                    Some(val) => self.machine.input(val),
+                    None => {
+                        self.finished = true;
+                        return Some(Err(self.machine.error(None, ErrorKind::InputExhausted)));
+                    }
+                },
+                Ok(_) => break,
+                Err(err) => {
+                    self.finished = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+
+        self.finished = true;
+        None
+    }
+}
+
+/// Runs the program synchronously, iterating over its outputs. If program
+/// fails, the error is the last item.
+pub fn try_run<W: Word>(
+    program: Vec<W>,
+    input: impl IntoIterator<Item = W>,
+) -> Outputs<W, impl FnMut() -> Option<W>> {
+    let mut input = input.into_iter();
+    Outputs::new(Machine::new(program), move || input.next())
+}
+
+/// Runs the program synchronously, iterating over its outputs. Panics if
+/// program fails.
+pub fn run<W: Word>(
+    program: Vec<W>,
+    input: impl IntoIterator<Item = W>,
+) -> impl Iterator<Item = W> {
+    try_run(program, input).map(|res| res.unwrap_or_else(|err| panic!("{}", err)))
+}
+
+/// Runs the program synchronously until it halts, discarding its outputs.
+/// Returns value left at address 0, which is how the earliest Intcode
+/// programs report their results.
+pub fn try_evaluate<W: Word>(
+    program: Vec<W>,
+    input: impl IntoIterator<Item = W>,
+) -> Result<W, IntcodeError> {
+    let mut outputs = try_run(program, input);
+    for res in &mut outputs {
+        res?;
+    }
+
+    Ok(outputs.machine().peek(0))
+}
+
+/// Runs the program synchronously until it halts, returning value left at
+/// address 0. Panics if program fails.
+pub fn evaluate<W: Word>(program: Vec<W>, input: impl IntoIterator<Item = W>) -> W {
+    try_evaluate(program, input).unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// Runs the program, yielding its outputs. If program fails, the error is
+/// yielded as the last stream item.
+pub fn try_interpret<W: Word, S: Stream<Item = W> + Unpin>(
+    program: Vec<W>,
     input: S,
-) -> impl Stream<Item = i128> {
+) -> impl Stream<Item = Result<W, IntcodeError>> {
+    try_interpret_on(Machine::new(program), input)
+}
+
+/// Same as `try_interpret`, but runs already configured machine
+pub fn try_interpret_on<W: Word, S: Stream<Item = W> + Unpin>(
+    machine: Machine<W>,
+    input: S,
+) -> impl Stream<Item = Result<W, IntcodeError>> {
     stream!(
-        let program = program;
         let mut input = input;
-        let mut pc = 0;
-        let mut machine = Machine {
-            memory: program,
-            relative_base: 0,
-        };
+        // Input is never available synchronously, it is awaited whenever
+        // machine reports it as exhausted
+        let mut outputs = Outputs::new(machine, || None);
+
+        while let Some(res) = outputs.next() {
+            if let Err(IntcodeError { kind: ErrorKind::InputExhausted, .. }) = res {
+                if let Some(val) = input.next().await {
+                    outputs.machine_mut().input(val);
+                    outputs.resume();
+                    continue;
+                }
+            }
 
-        while let Some((output, new_pc)) = handle_opcode(pc, &mut machine, &mut input).await {
-            yield output;
-            pc = new_pc;
+            let failed = res.is_err();
+            yield res;
+            if failed {
+                break;
+            }
         }
     )
-    .filter_map(std::convert::identity)
 }
 
+/// Runs the program, yielding its outputs. Panics if program fails.
+pub fn interpret<W: Word, S: Stream<Item = W> + Unpin>(
+    program: Vec<W>,
+    input: S,
+) -> impl Stream<Item = W> {
+    try_interpret(program, input).map(|res| res.unwrap_or_else(|err| panic!("{}", err)))
+}
+
+/// Reads program from stream of lines (see `parse::read`). Panics with
+/// position of invalid value if program is malformed.
 pub async fn parse_program<S: Stream<Item = String> + Unpin>(input: &mut S) -> Vec<i128> {
-    input
-        .next()
+    parse::read(input)
         .await
-        .unwrap()
-        .split(',')
-        .map(str::parse::<i128>)
-        .filter_map(Result::ok)
-        .collect::<Vec<_>>()
+        .unwrap_or_else(|err| panic!("Invalid program: {}", err))
 }
 
+#[cfg(test)]
+mod tests {
+    use super::{
+        interpret, run, try_interpret, try_run, ErrorKind, IntcodeError, Machine, Outputs, State,
+    };
+    use async_std::prelude::*;
+    use async_std::stream::{self, from_iter};
+    use futures_util::pin_mut;
+
+    async fn failure(program: Vec<i128>, input: Vec<i128>) -> IntcodeError {
+        let results = try_interpret(program, from_iter(input));
+        pin_mut!(results);
+        results.filter_map(Result::err).next().await.unwrap()
+    }
+
+    #[async_std::test]
+    async fn interpret_test() -> std::io::Result<()> {
+        let program: Vec<i128> = vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+        let output = interpret(program.clone(), stream::once(8));
+        pin_mut!(output);
+        assert_eq!(vec![1], output.collect::<Vec<_>>().await);
+        let output = interpret(program, stream::once(7));
+        pin_mut!(output);
+        assert_eq!(vec![0], output.collect::<Vec<_>>().await);
+
+        Ok(())
+    }
+
+    #[test]
+    fn run_test() {
+        // Outputs doubled inputs until 0 is read
+        let program: Vec<i128> = vec![
+            3, 15, 1006, 15, 14, 102, 2, 15, 15, 4, 15, 1105, 1, 0, 99, 0,
+        ];
+        assert_eq!(
+            vec![2, 4],
+            run(program.clone(), vec![1, 2, 0]).collect::<Vec<_>>()
+        );
+
+        let mut next = 0;
+        let mut outputs = Outputs::new(Machine::new(program.clone()), || {
+            next += 1;
+            if next <= 2 {
+                Some(next)
+            } else {
+                None
+            }
+        });
+        assert_eq!(Some(Ok(2)), outputs.next());
+        assert_eq!(Some(Ok(4)), outputs.next());
+        assert_eq!(
+            Some(ErrorKind::InputExhausted),
+            outputs.next().and_then(Result::err).map(|err| err.kind)
+        );
+        assert_eq!(None, outputs.next());
+        outputs.machine_mut().input(5);
+        outputs.machine_mut().input(0);
+        outputs.resume();
+        assert_eq!(vec![Ok(10)], outputs.collect::<Vec<_>>());
+
+        assert_eq!(
+            vec![Ok(6), Err(ErrorKind::InputExhausted)],
+            try_run(program, vec![3])
+                .map(|res| res.map_err(|err| err.kind))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn far_memory_test() {
+        // Stores input at far address and relative to far base, then
+        // outputs both
+        let far: i128 = 1 << 40;
+        let mut machine = Machine::new(vec![3, far, 109, far, 203, 5, 4, far, 204, 5, 99]);
+        machine.input(1);
+        machine.input(2);
+        assert_eq!(Ok(State::Output(1)), machine.run_until_event());
+        assert_eq!(Ok(State::Output(2)), machine.run_until_event());
+        assert_eq!(Ok(State::Halted), machine.run_until_event());
+        assert_eq!(11, machine.memory().image().len());
+    }
+
+    #[test]
+    fn decode_cache_test() {
+        // Outputs 1, then overwrites its first instruction with one
+        // outputting 2 and jumps back
+        let program: Vec<i128> = vec![104, 1, 1101, 0, 2, 1, 1106, 0, 0];
+        for &enabled in &[true, false] {
+            let mut machine = Machine::new(program.clone());
+            machine.set_decode_cache(enabled);
+            assert_eq!(Ok(State::Output(1)), machine.run_until_event());
+            assert_eq!(Ok(State::Output(2)), machine.run_until_event());
+            machine.poke(3, 3);
+            assert_eq!(Ok(State::Output(5)), machine.run_until_event());
+        }
+    }
+
+    #[test]
+    fn top_address_test() {
+        // Writes to the last address and reads it back
+        let top = usize::MAX as i128;
+        let mut machine: Machine = Machine::new(vec![1101, 1, 0, top, 4, top, 99]);
+        assert_eq!(Ok(State::Output(1)), machine.run_until_event());
+        assert_eq!(Ok(State::Halted), machine.run_until_event());
+        machine.poke(usize::MAX, 2);
+        assert_eq!(2, machine.peek(usize::MAX));
+    }
+
+    #[test]
+    fn machine_test() {
+        let mut machine: Machine = Machine::new(vec![3, 9, 1001, 9, 1, 9, 4, 9, 99, 0]);
+        assert_eq!(Ok(State::NeedsInput), machine.run_until_event());
+        assert_eq!(0, machine.pc());
+        machine.input(41);
+        assert_eq!(Ok(State::Running), machine.step());
+        assert_eq!(Ok(State::Output(42)), machine.run_until_event());
+        assert_eq!(Ok(State::Halted), machine.run_until_event());
+        assert_eq!(8, machine.pc());
+        assert_eq!(42, machine.memory()[9]);
+    }
+
+    #[test]
+    fn pc_overflow_test() {
+        let top = usize::MAX;
+        let error = |pc, argument, addr| IntcodeError {
+            pc,
+            opcode: 0,
+            argument,
+            kind: ErrorKind::AddressOutOfRange(addr),
+        };
+
+        // Output instruction at the very end of address space, executing it
+        // would move pc beyond it
+        let mut machine: Machine = Machine::new(vec![1106, 0, top as i128 - 1]);
+        machine.poke(top - 1, 104);
+        machine.poke(top, 7);
+        assert_eq!(Ok(State::Running), machine.step());
+        assert_eq!(
+            Err(IntcodeError {
+                opcode: 104,
+                ..error(top - 1, None, top as i128 + 1)
+            }),
+            machine.step()
+        );
+        assert_eq!(top - 1, machine.pc());
+
+        // Arguments of instruction don't fit in address space
+        let mut machine: Machine = Machine::new(vec![1106, 0, top as i128]);
+        machine.poke(top, 1101);
+        assert_eq!(Ok(State::Running), machine.step());
+        assert_eq!(
+            Err(IntcodeError {
+                opcode: 1101,
+                ..error(top, Some(0), top as i128 + 1)
+            }),
+            machine.step()
+        );
+
+        // Jump at the end of address space is fine as long as it is taken
+        let mut machine: Machine = Machine::new(vec![1105, 1, top as i128 - 2, 104, 5, 99]);
+        machine.poke(top - 2, 1105);
+        machine.poke(top - 1, 1);
+        machine.poke(top, 3);
+        machine.enable_coverage();
+        assert_eq!(Ok(State::Output(5)), machine.run_until_event());
+        assert_eq!(Ok(State::Halted), machine.run_until_event());
+    }
+
+    #[async_std::test]
+    async fn errors_test() -> std::io::Result<()> {
+        assert_eq!(
+            IntcodeError {
+                pc: 4,
+                opcode: 42,
+                argument: None,
+                kind: ErrorKind::InvalidOpcode,
+            },
+            failure(vec![1101, 1, 1, 0, 42], vec![]).await
+        );
+        assert_eq!(
+            IntcodeError {
+                pc: 0,
+                opcode: 301,
+                argument: Some(0),
+                kind: ErrorKind::InvalidMode(3),
+            },
+            failure(vec![301, 1, 1, 0, 99], vec![]).await
+        );
+        assert_eq!(
+            IntcodeError {
+                pc: 0,
+                opcode: 11101,
+                argument: Some(2),
+                kind: ErrorKind::WriteToImmediate,
+            },
+            failure(vec![11101, 1, 1, 0, 99], vec![]).await
+        );
+        assert_eq!(
+            IntcodeError {
+                pc: 2,
+                opcode: 204,
+                argument: Some(0),
+                kind: ErrorKind::NegativeAddress(-3),
+            },
+            failure(vec![109, -1, 204, -2, 99], vec![]).await
+        );
+        assert_eq!(
+            IntcodeError {
+                pc: 0,
+                opcode: 4,
+                argument: Some(0),
+                kind: ErrorKind::AddressOutOfRange(1 << 100),
+            },
+            failure(vec![4, 1 << 100, 99], vec![]).await
+        );
+        assert_eq!(
+            IntcodeError {
+                pc: 2,
+                opcode: 3,
+                argument: None,
+                kind: ErrorKind::InputExhausted,
+            },
+            failure(vec![3, 0, 3, 0, 99], vec![5]).await
+        );
+
+        // Input is not consumed by failed read
+        let mut machine: Machine = Machine::new(vec![103, 0, 99]);
+        machine.input(5);
+        assert_eq!(
+            Err(IntcodeError {
+                pc: 0,
+                opcode: 103,
+                argument: Some(0),
+                kind: ErrorKind::WriteToImmediate,
+            }),
+            machine.step()
+        );
+        assert_eq!(1, machine.pending_input());
+
+        Ok(())
+    }
+}
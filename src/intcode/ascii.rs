@@ -0,0 +1,134 @@
+use super::interpret;
+use super::word::Word;
+use async_std::prelude::*;
+use async_std::stream::Stream;
+use async_stream::stream;
+use futures_util::pin_mut;
+
+/// Output of ASCII program
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ascii<W = i128> {
+    /// Line of text, without terminating newline
+    Line(String),
+    /// Value which is not an ASCII character
+    Raw(W),
+}
+
+/// Decoder of ASCII program output, fed one value at a time
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Decoder {
+    line: String,
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes next output value, returns line if it is complete
+    pub fn push<W: Word>(&mut self, val: W) -> Option<Ascii<W>> {
+        match val.to_i128() {
+            Some(10) => Some(Ascii::Line(std::mem::take(&mut self.line))),
+            Some(c) if (0..=127).contains(&c) => {
+                self.line.push(c as u8 as char);
+                None
+            }
+            _ => Some(Ascii::Raw(val)),
+        }
+    }
+
+    /// Returns unterminated line, if any
+    pub fn finish<W>(&mut self) -> Option<Ascii<W>> {
+        if self.line.is_empty() {
+            None
+        } else {
+            Some(Ascii::Line(std::mem::take(&mut self.line)))
+        }
+    }
+}
+
+/// Encodes line of input, with terminating newline
+pub fn encode<W: Word>(line: &str) -> impl Iterator<Item = W> + '_ {
+    line.bytes()
+        .chain(Some(b'\n'))
+        .map(|c| W::from_i64(c as i64))
+}
+
+/// Runs ASCII program, passing it input lines terminated with newline, and
+/// yielding its output decoded to lines. Raw values are yielded as soon as
+/// they are produced, even in the middle of a line. Last line is yielded even
+/// if program doesn't terminate it. Panics if program fails.
+pub fn interpret_ascii<W: Word, S: Stream<Item = String> + Unpin>(
+    program: Vec<W>,
+    input: S,
+) -> impl Stream<Item = Ascii<W>> {
+    let input = Box::pin(stream! {
+        let mut input = input;
+        while let Some(line) = input.next().await {
+            for val in encode(&line) {
+                yield val;
+            }
+        }
+    });
+
+    stream! {
+        let output = interpret(program, input);
+        pin_mut!(output);
+        let mut decoder = Decoder::new();
+
+        while let Some(val) = output.next().await {
+            if let Some(ascii) = decoder.push(val) {
+                yield ascii;
+            }
+        }
+
+        if let Some(ascii) = decoder.finish() {
+            yield ascii;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{interpret_ascii, Ascii};
+    use crate::intcode::asm::assemble;
+    use async_std::prelude::*;
+    use async_std::stream;
+    use futures_util::pin_mut;
+
+    #[async_std::test]
+    async fn ascii_test() -> std::io::Result<()> {
+        // Greets, echoes single line and outputs 1000
+        let program = assemble(
+            "
+                    WRT #72
+                    WRT #105
+                    WRT #10
+            loop:   READ [c]
+                    WRT [c]
+                    EQ [c], #10, [end]
+                    JMPF [end], #loop
+                    WRT #1000
+                    WRT #33
+                    EXIT
+            c:      DATA 0
+            end:    DATA 0
+            ",
+        )
+        .unwrap();
+
+        let output = interpret_ascii(program, stream::once("echo".to_owned()));
+        pin_mut!(output);
+        assert_eq!(
+            vec![
+                Ascii::Line("Hi".to_owned()),
+                Ascii::Line("echo".to_owned()),
+                Ascii::Raw(1000),
+                Ascii::Line("!".to_owned()),
+            ],
+            output.collect::<Vec<_>>().await
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,416 @@
+//! Intcode assembly language. Every line consists of optional labels,
+//! optional statement and optional comment:
+//!
+//! ```text
+//! loop:   ADD [counter], #-1, [counter]  ; decrement counter
+//!         JMPT [counter], #loop
+//!         EXIT
+//! counter: DATA 10
+//! ```
+//!
+//! Statements are instructions (mnemonics as printed by disassembler, or
+//! `EXIT` for termination opcode), `DATA` directive emitting listed values,
+//! `RESERVE` directive emitting given number of zeros, and `NAME = expr`
+//! constant definitions. Instruction arguments are `#expr` for immediate,
+//! `[expr]` for positional, and `rb+expr` / `rb-expr` / `rb` for relative
+//! mode. Expressions consist of numbers, symbols, `$` (address of current
+//! statement), `+`, `-`, `*` and parentheses.
+
+use super::Op;
+use nom::{
+    branch::alt,
+    bytes::complete::{tag_no_case, take_while1},
+    character::complete::{char, digit1, space0},
+    combinator::{all_consuming, map, map_res, opt, rest, verify},
+    multi::{fold_many0, many0, separated_list},
+    sequence::{delimited, pair, preceded, terminated},
+    IResult,
+};
+use std::collections::HashMap;
+use std::fmt;
+
+const OPS: [Op; 9] = [
+    Op::Add,
+    Op::Mul,
+    Op::Read,
+    Op::Write,
+    Op::JmpT,
+    Op::JmpF,
+    Op::Less,
+    Op::Equal,
+    Op::MoveBase,
+];
+
+// Programs are never larger than this, so reserving memory by mistake
+// doesn't exhaust it
+const MAX_SIZE: usize = 1 << 24;
+
+/// Assembly failure
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsmError {
+    /// Number of failing line, starting from 1
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Num(i128),
+    Symbol(String),
+    Here,
+    Neg(Box<Expr>),
+    Bin(Box<Expr>, char, Box<Expr>),
+}
+
+#[derive(Debug)]
+enum Operand {
+    Imm(Expr),
+    Pos(Expr),
+    Rel(Expr),
+}
+
+#[derive(Debug)]
+enum Statement {
+    Instruction(String, Vec<Operand>),
+    Data(Vec<Expr>),
+    Reserve(Expr),
+    Constant(String, Expr),
+}
+
+fn ws<'a, O>(
+    parser: impl Fn(&'a str) -> IResult<&'a str, O>,
+) -> impl Fn(&'a str) -> IResult<&'a str, O> {
+    delimited(space0, parser, space0)
+}
+
+fn ident(input: &str) -> IResult<&str, &str> {
+    verify(
+        take_while1(|c: char| c.is_alphanumeric() || c == '_'),
+        |s: &str| !s.starts_with(|c: char| c.is_ascii_digit()),
+    )(input)
+}
+
+fn number(input: &str) -> IResult<&str, i128> {
+    map_res(digit1, str::parse)(input)
+}
+
+fn factor(input: &str) -> IResult<&str, Expr> {
+    ws(alt((
+        map(number, Expr::Num),
+        map(ident, |s| Expr::Symbol(s.to_owned())),
+        map(char('$'), |_| Expr::Here),
+        map(preceded(char('-'), factor), |e| Expr::Neg(Box::new(e))),
+        delimited(char('('), expr, char(')')),
+    )))(input)
+}
+
+fn term(input: &str) -> IResult<&str, Expr> {
+    let (input, init) = factor(input)?;
+    fold_many0(pair(ws(char('*')), factor), init, |acc, (op, e)| {
+        Expr::Bin(Box::new(acc), op, Box::new(e))
+    })(input)
+}
+
+// Sum of terms following already parsed `init`
+fn sum<'a>(init: Expr) -> impl Fn(&'a str) -> IResult<&'a str, Expr> {
+    fold_many0(
+        pair(ws(alt((char('+'), char('-')))), term),
+        init,
+        |acc, (op, e)| Expr::Bin(Box::new(acc), op, Box::new(e)),
+    )
+}
+
+fn expr(input: &str) -> IResult<&str, Expr> {
+    let (input, init) = term(input)?;
+    sum(init)(input)
+}
+
+fn operand(input: &str) -> IResult<&str, Operand> {
+    ws(alt((
+        map(preceded(char('#'), expr), Operand::Imm),
+        map(delimited(char('['), expr, char(']')), Operand::Pos),
+        map(preceded(tag_no_case("rb"), sum(Expr::Num(0))), Operand::Rel),
+    )))(input)
+}
+
+fn statement(input: &str) -> IResult<&str, Statement> {
+    let (input, name) = ws(ident)(input)?;
+    if let (input, Some(_)) = opt(char('='))(input)? {
+        return map(expr, |e| Statement::Constant(name.to_owned(), e))(input);
+    }
+
+    match name.to_ascii_uppercase().as_str() {
+        "DATA" => map(separated_list(char(','), expr), Statement::Data)(input),
+        "RESERVE" => map(expr, Statement::Reserve)(input),
+        _ => map(separated_list(char(','), operand), |ops| {
+            Statement::Instruction(name.to_owned(), ops)
+        })(input),
+    }
+}
+
+fn line(input: &str) -> IResult<&str, (Vec<&str>, Option<Statement>)> {
+    all_consuming(terminated(
+        pair(many0(terminated(ws(ident), char(':'))), opt(statement)),
+        preceded(space0, opt(preceded(char(';'), rest))),
+    ))(input)
+}
+
+fn parse_line(input: &str) -> Result<(Vec<&str>, Option<Statement>), String> {
+    match line(input) {
+        Ok((_, parsed)) => Ok(parsed),
+        Err(nom::Err::Error((rest, _))) | Err(nom::Err::Failure((rest, _)))
+            if !rest.trim().is_empty() =>
+        {
+            Err(format!("unexpected `{}`", rest.trim()))
+        }
+        Err(_) => Err("unexpected end of line".to_owned()),
+    }
+}
+
+// Symbols table: name -> (defining line, value)
+type Symbols = HashMap<String, (usize, Expr)>;
+
+fn eval(expr: &Expr, symbols: &Symbols, here: usize, depth: usize) -> Result<i128, String> {
+    let res = match expr {
+        Expr::Num(n) => Some(*n),
+        Expr::Here => Some(here as i128),
+        Expr::Symbol(name) => {
+            let (_, expr) = symbols
+                .get(name)
+                .ok_or_else(|| format!("undefined symbol `{}`", name))?;
+            if depth > symbols.len() {
+                return Err(format!("recursive definition of symbol `{}`", name));
+            }
+            Some(eval(expr, symbols, here, depth + 1)?)
+        }
+        Expr::Neg(e) => eval(e, symbols, here, depth)?.checked_neg(),
+        Expr::Bin(a, op, b) => {
+            let a = eval(a, symbols, here, depth)?;
+            let b = eval(b, symbols, here, depth)?;
+            match op {
+                '+' => a.checked_add(b),
+                '-' => a.checked_sub(b),
+                _ => a.checked_mul(b),
+            }
+        }
+    };
+
+    res.ok_or_else(|| "arithmetic overflow".to_owned())
+}
+
+fn define(symbols: &mut Symbols, name: &str, line: usize, value: Expr) -> Result<(), String> {
+    if let Some((prev, _)) = symbols.get(name) {
+        return Err(format!(
+            "symbol `{}` already defined at line {}",
+            name, prev
+        ));
+    }
+
+    symbols.insert(name.to_owned(), (line, value));
+    Ok(())
+}
+
+fn encode(
+    name: &str,
+    operands: &[Operand],
+    symbols: &Symbols,
+    here: usize,
+) -> Result<Vec<i128>, String> {
+    let op = OPS
+        .iter()
+        .find(|op| op.mnemonic().eq_ignore_ascii_case(name));
+    let (code, args) = match op {
+        Some(op) => (op.code(), op.args()),
+        None if name.eq_ignore_ascii_case("EXIT") => (99, 0),
+        None => return Err(format!("unknown mnemonic `{}`", name)),
+    };
+
+    if operands.len() != args {
+        return Err(format!(
+            "{} takes {} arguments, {} given",
+            name.to_ascii_uppercase(),
+            args,
+            operands.len()
+        ));
+    }
+
+    let mut encoded = vec![code];
+    let mut mode_base = 100;
+    for (idx, operand) in operands.iter().enumerate() {
+        let (mode, expr) = match operand {
+            Operand::Pos(e) => (0, e),
+            Operand::Imm(e) => (1, e),
+            Operand::Rel(e) => (2, e),
+        };
+        let value = eval(expr, symbols, here, 0)?;
+
+        if mode == 0 && value < 0 {
+            return Err(format!("argument {}: negative address {}", idx + 1, value));
+        }
+        if mode == 1 && op.and_then(Op::output_arg) == Some(idx) {
+            return Err(format!("argument {}: output cannot be immediate", idx + 1));
+        }
+
+        encoded[0] += mode * mode_base;
+        mode_base *= 10;
+        encoded.push(value);
+    }
+
+    Ok(encoded)
+}
+
+/// Assembles program from its textual representation
+pub fn assemble(source: &str) -> Result<Vec<i128>, AsmError> {
+    let mut symbols = Symbols::new();
+    let mut statements = vec![];
+    let mut addr = 0;
+
+    // First pass: collect symbols and calculate statements placement
+    for (idx, text) in source.lines().enumerate() {
+        let line = idx + 1;
+        let err = |message| AsmError { line, message };
+        let (labels, statement) = parse_line(text).map_err(err)?;
+
+        for label in labels {
+            define(&mut symbols, label, line, Expr::Num(addr as i128)).map_err(err)?;
+        }
+
+        let size = match &statement {
+            None => continue,
+            Some(Statement::Constant(name, expr)) => {
+                define(&mut symbols, name, line, expr.clone()).map_err(err)?;
+                continue;
+            }
+            Some(Statement::Instruction(_, operands)) => operands.len() + 1,
+            Some(Statement::Data(values)) => values.len(),
+            Some(Statement::Reserve(expr)) => match eval(expr, &symbols, addr, 0).map_err(err)? {
+                n if n < 0 => return Err(err(format!("negative reservation size {}", n))),
+                n if n > MAX_SIZE as i128 - addr as i128 => {
+                    return Err(err(format!(
+                        "reservation of {} cells exceeds program size limit of {}",
+                        n, MAX_SIZE
+                    )))
+                }
+                n => n as usize,
+            },
+        };
+
+        statements.push((line, addr, size, statement));
+        addr += size;
+    }
+
+    // Second pass: emit code
+    let mut program = Vec::with_capacity(addr);
+    for (line, addr, size, statement) in statements {
+        let err = |message| AsmError { line, message };
+        match statement {
+            Some(Statement::Instruction(name, operands)) => {
+                program.extend(encode(&name, &operands, &symbols, addr).map_err(err)?)
+            }
+            Some(Statement::Data(values)) => {
+                for value in values {
+                    program.push(eval(&value, &symbols, addr, 0).map_err(err)?);
+                }
+            }
+            _ => program.resize(addr + size, 0),
+        }
+    }
+
+    Ok(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{assemble, AsmError};
+    use crate::intcode::{Machine, State};
+
+    #[test]
+    fn assemble_test() {
+        let program = assemble(
+            "
+            ; counts down from input to 1
+                    READ [counter]
+            loop:   WRT [counter]
+                    add [counter], #-1, [counter]
+                    JMPT [counter], #loop
+                    EXIT
+            counter: DATA 0
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            vec![3, 12, 4, 12, 1001, 12, -1, 12, 1005, 12, 2, 99, 0],
+            program
+        );
+
+        let mut machine = Machine::new(program);
+        machine.input(2);
+        assert_eq!(Ok(State::Output(2)), machine.run_until_event());
+        assert_eq!(Ok(State::Output(1)), machine.run_until_event());
+        assert_eq!(Ok(State::Halted), machine.run_until_event());
+    }
+
+    #[test]
+    fn expressions_test() {
+        let program = assemble(
+            "
+            SIZE = 2 * (end - start)
+            start:  MVB #stack+SIZE
+                    ADD rb + 1, rb-1, rb
+                    EQ #$, [end-1], rb+SIZE*-1
+            end:    RESERVE 2
+            stack:  DATA -1, 2 - -3
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            vec![109, 32, 22201, 1, -1, 0, 20108, 6, 9, -20, 0, 0, -1, 5],
+            program
+        );
+    }
+
+    #[test]
+    fn errors_test() {
+        let err = |line, message: &str| {
+            Err(AsmError {
+                line,
+                message: message.to_owned(),
+            })
+        };
+
+        assert_eq!(err(2, "unknown mnemonic `JMP`"), assemble("EXIT\nJMP #0"));
+        assert_eq!(
+            err(1, "ADD takes 3 arguments, 2 given"),
+            assemble("ADD #1, #2")
+        );
+        assert_eq!(
+            err(1, "argument 3: output cannot be immediate"),
+            assemble("ADD #1, #2, #3")
+        );
+        assert_eq!(err(1, "undefined symbol `foo`"), assemble("WRT [foo]"));
+        assert_eq!(
+            err(3, "symbol `a` already defined at line 1"),
+            assemble("a: EXIT\n\na: EXIT")
+        );
+        assert_eq!(err(1, "unexpected `[1`"), assemble("WRT [1"));
+        assert_eq!(
+            err(3, "recursive definition of symbol `b`"),
+            assemble("a = b\nb = a\nWRT #a")
+        );
+        assert_eq!(
+            err(
+                2,
+                "reservation of 18446744073709551615 cells exceeds program size limit of 16777216"
+            ),
+            assemble("EXIT\nRESERVE 18446744073709551615")
+        );
+    }
+}
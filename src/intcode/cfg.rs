@@ -0,0 +1,301 @@
+use super::disasm::{disassemble_from, Line};
+use super::trace::{Event, Tracer};
+use super::word::Word;
+use super::{Argument, Op};
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+
+/// How control flows between basic blocks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EdgeKind {
+    /// Execution continues with next instruction
+    Fallthrough,
+    /// Jump to immediate address
+    Jump,
+    /// Computed jump observed in dynamic trace
+    Dynamic,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edge {
+    /// Address of first instruction of source block
+    pub from: usize,
+    /// Address of first instruction of target block
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+/// Sequence of instructions which is always entered at its first
+/// instruction and left after its last one
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub lines: Vec<Line>,
+    /// Block ends with jump to computed address; it may have more
+    /// successors than its edges show
+    pub unresolved: bool,
+}
+
+impl Block {
+    pub fn start(&self) -> usize {
+        self.lines[0].addr()
+    }
+
+    /// Address right after the last instruction of block
+    pub fn end(&self) -> usize {
+        let last = &self.lines[self.lines.len() - 1];
+        last.addr() + last.size()
+    }
+}
+
+/// Control flow graph of program. Jumps to immediate addresses are resolved
+/// statically, computed jumps may be resolved with jumps observed while
+/// running the program (see `JumpRecorder`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cfg {
+    blocks: Vec<Block>,
+    edges: Vec<Edge>,
+}
+
+impl Cfg {
+    pub fn new(program: &[i128]) -> Self {
+        Self::with_jumps(program, &BTreeSet::new())
+    }
+
+    /// Builds graph using additional dynamic jumps, as (pc, target) pairs
+    pub fn with_jumps(program: &[i128], jumps: &BTreeSet<(usize, usize)>) -> Self {
+        let entries = Some(0).into_iter().chain(jumps.iter().map(|(_, to)| *to));
+        let listing = disassemble_from(program, entries);
+        let dynamic: BTreeSet<_> = jumps.iter().map(|(_, to)| *to).collect();
+
+        let mut blocks: Vec<Block> = vec![];
+        let mut leader = true;
+        for line in listing.lines() {
+            let op = match line {
+                Line::Instruction { op, .. } => Some(*op),
+                Line::Exit { .. } => None,
+                Line::Data { .. } => {
+                    leader = true;
+                    continue;
+                }
+            };
+
+            let addr = line.addr();
+            if leader || listing.is_target(addr) || dynamic.contains(&addr) {
+                blocks.push(Block {
+                    lines: vec![],
+                    unresolved: false,
+                });
+            }
+
+            let block = blocks.last_mut().unwrap();
+            block.lines.push(line.clone());
+            leader = match op {
+                Some(Op::JmpT) | Some(Op::JmpF) | None => true,
+                Some(_) => false,
+            };
+        }
+
+        let starts: BTreeSet<_> = blocks.iter().map(Block::start).collect();
+        let mut edges = vec![];
+        for block in &mut blocks {
+            let (from, end) = (block.start(), block.end());
+            let mut edge = |to, kind| {
+                if starts.contains(&to) {
+                    edges.push(Edge { from, to, kind });
+                }
+            };
+
+            let last = &block.lines[block.lines.len() - 1];
+            let (op, args) = match last {
+                Line::Instruction { op, args, .. } => (*op, args),
+                _ => continue,
+            };
+
+            let (taken, fallthrough) = match (op, &args[0]) {
+                (Op::JmpT, Argument::Imm(c)) => (*c != 0, *c == 0),
+                (Op::JmpF, Argument::Imm(c)) => (*c == 0, *c != 0),
+                (Op::JmpT, _) | (Op::JmpF, _) => (true, true),
+                _ => (false, true),
+            };
+
+            if taken {
+                match args[1] {
+                    Argument::Imm(target) if target >= 0 => edge(target as usize, EdgeKind::Jump),
+                    Argument::Imm(_) => (),
+                    _ => {
+                        block.unresolved = true;
+                        for (_, to) in jumps.range((last.addr(), 0)..=(last.addr(), usize::MAX)) {
+                            edge(*to, EdgeKind::Dynamic);
+                        }
+                    }
+                }
+            }
+
+            if fallthrough {
+                edge(end, EdgeKind::Fallthrough);
+            }
+        }
+
+        Self { blocks, edges }
+    }
+
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    /// Graph in Graphviz DOT format. Blocks with unresolved computed jumps
+    /// are drawn red, dynamic edges are dashed.
+    pub fn to_dot(&self) -> String {
+        let mut dot = "digraph cfg {\n    node [shape=box, fontname=monospace];\n".to_owned();
+
+        for block in &self.blocks {
+            let label: String = block
+                .lines
+                .iter()
+                .map(|line| format!("{}\\l", line).replace('"', "\\\""))
+                .collect();
+            let color = if block.unresolved { ", color=red" } else { "" };
+            writeln!(
+                dot,
+                "    b{} [label=\"{}\"{}];",
+                block.start(),
+                label,
+                color
+            )
+            .unwrap();
+        }
+
+        for edge in &self.edges {
+            let style = match edge.kind {
+                EdgeKind::Fallthrough => "",
+                EdgeKind::Jump => " [color=blue]",
+                EdgeKind::Dynamic => " [style=dashed]",
+            };
+            writeln!(dot, "    b{} -> b{}{};", edge.from, edge.to, style).unwrap();
+        }
+
+        dot += "}\n";
+        dot
+    }
+}
+
+/// Tracer collecting taken jumps to computed addresses. Clones share
+/// collected jumps, so one clone may be passed to machine, and another used
+/// to read them.
+#[derive(Debug, Clone, Default)]
+pub struct JumpRecorder {
+    jumps: Arc<Mutex<BTreeSet<(usize, usize)>>>,
+}
+
+impl JumpRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Recorded jumps as (pc, target) pairs
+    pub fn jumps(&self) -> BTreeSet<(usize, usize)> {
+        self.jumps.lock().unwrap().clone()
+    }
+}
+
+impl<W: Word> Tracer<W> for JumpRecorder {
+    fn trace(&mut self, event: &Event<W>) {
+        let jump_if_zero = match event.op {
+            Some(Op::JmpT) => false,
+            Some(Op::JmpF) => true,
+            _ => return,
+        };
+
+        if let (Argument::Imm(_), _) | (_, None) = (&event.args[1], event.values.get(1)) {
+            return;
+        }
+
+        if event.values[0].is_zero() == jump_if_zero {
+            let target = event.values[1].to_i128().map(usize::try_from);
+            if let Some(Ok(target)) = target {
+                self.jumps.lock().unwrap().insert((event.pc, target));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cfg, EdgeKind, JumpRecorder};
+    use crate::intcode::asm::assemble;
+    use crate::intcode::{Machine, State};
+
+    #[test]
+    fn cfg_test() {
+        // Outputs numbers from input down to 1, using subroutine
+        let program = assemble(
+            "
+                    READ [x]
+            loop:   JMPF [x], #done
+                    ADD #ret, #0, [retaddr]
+                    JMPT #1, #sub
+            ret:    ADD [x], #-1, [x]
+                    JMPT #1, #loop
+            done:   EXIT
+            sub:    WRT [x]
+                    JMPT #1, [retaddr]
+            x:      DATA 0
+            retaddr: DATA 0
+            ",
+        )
+        .unwrap();
+
+        let cfg = Cfg::new(&program);
+        let blocks: Vec<_> = cfg
+            .blocks()
+            .iter()
+            .map(|b| (b.start(), b.end(), b.unresolved))
+            .collect();
+        assert_eq!(
+            vec![
+                (0, 2, false),
+                (2, 5, false),
+                (5, 12, false),
+                (12, 19, false),
+                (19, 20, false),
+                (20, 25, true)
+            ],
+            blocks
+        );
+        let edges: Vec<_> = cfg.edges().iter().map(|e| (e.from, e.to, e.kind)).collect();
+        assert_eq!(
+            vec![
+                (0, 2, EdgeKind::Fallthrough),
+                (2, 19, EdgeKind::Jump),
+                (2, 5, EdgeKind::Fallthrough),
+                (5, 20, EdgeKind::Jump),
+                (12, 2, EdgeKind::Jump),
+            ],
+            edges
+        );
+
+        let recorder = JumpRecorder::new();
+        let mut machine = Machine::new(program.clone());
+        machine.set_tracer(Some(Box::new(recorder.clone())));
+        machine.input(2);
+        while let Ok(State::Output(_)) = machine.run_until_event() {}
+
+        let cfg = Cfg::with_jumps(&program, &recorder.jumps());
+        assert_eq!(
+            Some((20, 12, EdgeKind::Dynamic)),
+            cfg.edges().last().map(|e| (e.from, e.to, e.kind))
+        );
+
+        let dot = cfg.to_dot();
+        assert!(dot.starts_with("digraph cfg {\n"));
+        assert!(dot.contains("    b19 [label=\"  19: [   99] EXIT\\l\"];\n"));
+        assert!(dot.contains("    b20 -> b12 [style=dashed];\n"));
+        assert!(dot.contains("    b2 -> b19 [color=blue];\n"));
+    }
+}
@@ -0,0 +1,680 @@
+use super::disasm::{disassemble, Line};
+use super::memory::Memory;
+use super::word::{Overflow, Word};
+use super::{address, decode, Argument, ErrorKind, Fault, IntcodeError, Machine, Op, State};
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::mem;
+use std::sync::Arc;
+
+// Registers and memory visible to compiled instructions
+struct Regs<W> {
+    memory: Memory<W>,
+    relative_base: isize,
+    input: VecDeque<W>,
+    overflow: Overflow,
+    // Cells covered by compiled instructions
+    code: Arc<Vec<bool>>,
+    // Set when compiled code was overwritten
+    modified: bool,
+}
+
+impl<W: Word> Regs<W> {
+    fn write(&mut self, addr: usize, val: W) {
+        if self.code.get(addr) == Some(&true) {
+            self.modified = true;
+        }
+        self.memory.set(addr, val);
+    }
+
+    fn relative(&self, offset: isize) -> Result<usize, ErrorKind> {
+        address(&(self.relative_base as i128 + offset as i128))
+    }
+}
+
+// What should happen after compiled instruction is executed
+enum Flow<W> {
+    Next,
+    Jump(usize),
+    Output(W),
+    NeedsInput,
+    Halt,
+}
+
+type Get<W> = Box<dyn Fn(&Regs<W>) -> Result<W, Fault> + Send + Sync>;
+type Put<W> = Box<dyn Fn(&mut Regs<W>, W) -> Result<(), Fault> + Send + Sync>;
+type Target<W> = Box<dyn Fn(&Regs<W>) -> Result<usize, Fault> + Send + Sync>;
+type Instr<W> = Box<dyn Fn(&mut Regs<W>) -> Result<Flow<W>, Fault> + Send + Sync>;
+
+fn get<W: Word>(idx: usize, arg: &Argument<W>) -> Get<W> {
+    match *arg {
+        Argument::Imm(ref v) => {
+            let v = v.clone();
+            Box::new(move |_| Ok(v.clone()))
+        }
+        Argument::Pos(addr) => Box::new(move |regs| Ok(regs.memory.get(addr))),
+        Argument::Rel(offset) => Box::new(move |regs| {
+            let addr = regs.relative(offset).map_err(|kind| (Some(idx), kind))?;
+            Ok(regs.memory.get(addr))
+        }),
+    }
+}
+
+fn put<W: Word>(idx: usize, arg: &Argument<W>) -> Put<W> {
+    match *arg {
+        Argument::Imm(_) => Box::new(move |_, _| Err((Some(idx), ErrorKind::WriteToImmediate))),
+        Argument::Pos(addr) => Box::new(move |regs, val| {
+            regs.write(addr, val);
+            Ok(())
+        }),
+        Argument::Rel(offset) => Box::new(move |regs, val| {
+            let addr = regs.relative(offset).map_err(|kind| (Some(idx), kind))?;
+            regs.write(addr, val);
+            Ok(())
+        }),
+    }
+}
+
+// Immediate jump targets are resolved at compile time
+fn target<W: Word>(arg: &Argument<W>) -> Target<W> {
+    match arg {
+        Argument::Imm(v) => {
+            let target = address(v).map_err(|kind| (Some(1), kind));
+            Box::new(move |_| target.clone())
+        }
+        arg => {
+            let arg = get(1, arg);
+            Box::new(move |regs| address(&arg(regs)?).map_err(|kind| (Some(1), kind)))
+        }
+    }
+}
+
+fn compile_instr<W: Word>(op: Op, args: &[Argument<W>]) -> Instr<W> {
+    match op {
+        Op::Add | Op::Mul => {
+            let (a, b, out) = (get(0, &args[0]), get(1, &args[1]), put(2, &args[2]));
+            Box::new(move |regs| {
+                let (a, b) = (a(regs)?, b(regs)?);
+                let res = match op {
+                    Op::Add => a.add(&b, regs.overflow),
+                    _ => a.mul(&b, regs.overflow),
+                };
+                out(regs, res.ok_or((None, ErrorKind::Overflow))?)?;
+                Ok(Flow::Next)
+            })
+        }
+        Op::Read => {
+            let out = put(0, &args[0]);
+            Box::new(move |regs| match regs.input.pop_front() {
+                // Input is kept if it cannot be stored
+                Some(val) => match out(regs, val.clone()) {
+                    Ok(()) => Ok(Flow::Next),
+                    Err(err) => {
+                        regs.input.push_front(val);
+                        Err(err)
+                    }
+                },
+                None => Ok(Flow::NeedsInput),
+            })
+        }
+        Op::Write => {
+            let a = get(0, &args[0]);
+            Box::new(move |regs| a(regs).map(Flow::Output))
+        }
+        Op::JmpT | Op::JmpF => {
+            let (cond, target) = (get(0, &args[0]), target(&args[1]));
+            let jump_if_zero = op == Op::JmpF;
+            Box::new(move |regs| {
+                if cond(regs)?.is_zero() == jump_if_zero {
+                    target(regs).map(Flow::Jump)
+                } else {
+                    Ok(Flow::Next)
+                }
+            })
+        }
+        Op::Less | Op::Equal => {
+            let (a, b, out) = (get(0, &args[0]), get(1, &args[1]), put(2, &args[2]));
+            Box::new(move |regs| {
+                let (a, b) = (a(regs)?, b(regs)?);
+                let res = match op {
+                    Op::Less => a < b,
+                    _ => a == b,
+                };
+                out(regs, W::from_i64(res as i64))?;
+                Ok(Flow::Next)
+            })
+        }
+        Op::MoveBase => {
+            let a = get(0, &args[0]);
+            Box::new(move |regs| {
+                let arg = a(regs)?;
+                regs.relative_base = arg
+                    .to_i128()
+                    .and_then(|arg| isize::try_from(arg).ok())
+                    .and_then(|arg| regs.relative_base.checked_add(arg))
+                    .ok_or((Some(0), ErrorKind::AddressOutOfRange(arg.clamp_i128())))?;
+                Ok(Flow::Next)
+            })
+        }
+    }
+}
+
+struct Program<W> {
+    image: Vec<W>,
+    // Compiled instruction and address of next one, by instruction address
+    instrs: Vec<Option<(Instr<W>, usize)>>,
+    code: Arc<Vec<bool>>,
+}
+
+/// Program translated ahead of time to chain of closures with all argument
+/// modes resolved. Only code statically reachable from address 0 (as found
+/// by disassembler) is compiled. Compiled program is cheap to clone and may
+/// be started any number of times.
+pub struct Compiled<W = i128> {
+    program: Arc<Program<W>>,
+}
+
+impl<W> Clone for Compiled<W> {
+    fn clone(&self) -> Self {
+        Self {
+            program: self.program.clone(),
+        }
+    }
+}
+
+impl<W: Word> Compiled<W> {
+    pub fn new(image: Vec<W>) -> Self {
+        let clamped: Vec<_> = image.iter().map(Word::clamp_i128).collect();
+        let mut instrs: Vec<_> = image.iter().map(|_| None).collect();
+        let mut code = vec![false; image.len()];
+
+        for line in disassemble(&clamped).lines() {
+            let instr: Instr<W> = match line {
+                Line::Instruction { addr, .. } => match decode(image.as_slice(), *addr) {
+                    Ok((op, args)) => compile_instr(op, &args[..op.args()]),
+                    Err(_) => continue,
+                },
+                Line::Exit { .. } => Box::new(|_| Ok(Flow::Halt)),
+                Line::Data { .. } => continue,
+            };
+
+            let (addr, next) = (line.addr(), line.addr() + line.size());
+            let end = next.min(code.len());
+            code[addr..end].iter_mut().for_each(|c| *c = true);
+            instrs[addr] = Some((instr, next));
+        }
+
+        Self {
+            program: Arc::new(Program {
+                image,
+                instrs,
+                code: Arc::new(code),
+            }),
+        }
+    }
+
+    /// Creates machine running compiled program from its beginning
+    pub fn start(&self) -> CompiledMachine<W> {
+        CompiledMachine {
+            program: self.program.clone(),
+            pc: 0,
+            regs: Regs {
+                memory: Memory::new(self.program.image.clone()),
+                relative_base: 0,
+                input: VecDeque::new(),
+                overflow: Overflow::default(),
+                code: self.program.code.clone(),
+                modified: false,
+            },
+            fallback: None,
+        }
+    }
+}
+
+/// Running compiled program. When program overwrites its compiled code, or
+/// jumps to code which was not compiled, execution falls back to the
+/// interpreter. Compiled code is neither traced nor limited.
+pub struct CompiledMachine<W = i128> {
+    program: Arc<Program<W>>,
+    pc: usize,
+    regs: Regs<W>,
+    fallback: Option<Machine<W>>,
+}
+
+impl<W: Word> CompiledMachine<W> {
+    /// Buffers value to be consumed by program reads
+    pub fn input(&mut self, val: W) {
+        match &mut self.fallback {
+            Some(machine) => machine.input(val),
+            None => self.regs.input.push_back(val),
+        }
+    }
+
+    /// Selects behavior of arithmetic overflow, by default it is an error
+    pub fn set_overflow(&mut self, overflow: Overflow) {
+        match &mut self.fallback {
+            Some(machine) => machine.set_overflow(overflow),
+            None => self.regs.overflow = overflow,
+        }
+    }
+
+    /// Checks if program still runs compiled code
+    pub fn is_compiled(&self) -> bool {
+        self.fallback.is_none()
+    }
+
+    pub fn pc(&self) -> usize {
+        self.fallback.as_ref().map_or(self.pc, Machine::pc)
+    }
+
+    pub fn relative_base(&self) -> isize {
+        self.fallback
+            .as_ref()
+            .map_or(self.regs.relative_base, Machine::relative_base)
+    }
+
+    pub fn memory(&self) -> &Memory<W> {
+        self.fallback
+            .as_ref()
+            .map_or(&self.regs.memory, Machine::memory)
+    }
+
+    fn fall_back(&mut self) -> &mut Machine<W> {
+        let regs = &mut self.regs;
+        let mut machine = Machine::new(vec![]);
+        machine.memory = mem::replace(&mut regs.memory, Memory::new(vec![]));
+        machine.pc = self.pc;
+        machine.relative_base = regs.relative_base;
+        machine.input = mem::take(&mut regs.input);
+        machine.overflow = regs.overflow;
+        self.fallback.get_or_insert(machine)
+    }
+
+    /// Executes instructions until machine halts, needs input or produces
+    /// output
+    pub fn run_until_event(&mut self) -> Result<State<W>, IntcodeError> {
+        if let Some(machine) = &mut self.fallback {
+            return machine.run_until_event();
+        }
+
+        loop {
+            let (instr, next) = match self.program.instrs.get(self.pc) {
+                Some(Some(instr)) => instr,
+                _ => return self.fall_back().run_until_event(),
+            };
+
+            let flow = instr(&mut self.regs).map_err(|(argument, kind)| IntcodeError {
+                pc: self.pc,
+                opcode: self.regs.memory.get(self.pc).clamp_i128(),
+                argument,
+                kind,
+            })?;
+
+            let event = match flow {
+                Flow::Next => {
+                    self.pc = *next;
+                    None
+                }
+                Flow::Jump(target) => {
+                    self.pc = target;
+                    None
+                }
+                Flow::Output(val) => {
+                    self.pc = *next;
+                    Some(State::Output(val))
+                }
+                Flow::NeedsInput => Some(State::NeedsInput),
+                Flow::Halt => Some(State::Halted),
+            };
+
+            if self.regs.modified {
+                self.fall_back();
+            }
+
+            if let Some(state) = event {
+                return Ok(state);
+            }
+
+            if self.fallback.is_some() {
+                return self.run_until_event();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Compiled, CompiledMachine};
+    use crate::intcode::asm::assemble;
+    use crate::intcode::memory::Memory;
+    use crate::intcode::{parse, try_run, ErrorKind, IntcodeError, Machine, State};
+    use std::collections::HashSet;
+    use std::env;
+    use std::path::Path;
+
+    /// Directory with puzzle inputs named `dayN.txt`, checked by
+    /// `inputs_test` if set
+    const INPUTS_VAR: &str = "AOC_INPUTS";
+
+    fn compiled_outputs(compiled: &Compiled, input: &[i128]) -> Vec<Result<i128, ErrorKind>> {
+        let mut machine = compiled.start();
+        let mut input = input.iter();
+        let mut outputs = vec![];
+        loop {
+            match machine.run_until_event() {
+                Ok(State::Output(val)) => outputs.push(Ok(val)),
+                Ok(State::NeedsInput) => match input.next() {
+                    Some(val) => machine.input(*val),
+                    None => {
+                        outputs.push(Err(ErrorKind::InputExhausted));
+                        return outputs;
+                    }
+                },
+                Ok(_) => return outputs,
+                Err(err) => {
+                    outputs.push(Err(err.kind));
+                    return outputs;
+                }
+            }
+        }
+    }
+
+    // Compares compiled program against interpreter
+    fn check(program: Vec<i128>, inputs: &[&[i128]]) {
+        let compiled = Compiled::new(program.clone());
+        for input in inputs {
+            let expected: Vec<_> = try_run(program.clone(), input.to_vec())
+                .map(|res| res.map_err(|err| err.kind))
+                .collect();
+            assert_eq!(expected, compiled_outputs(&compiled, input));
+        }
+    }
+
+    // Machine driven by `run_session`
+    trait Runner {
+        fn run_until_event(&mut self) -> Result<State, IntcodeError>;
+        fn input(&mut self, val: i128);
+        fn state(&self) -> (usize, isize, Memory);
+    }
+
+    impl Runner for Machine {
+        fn run_until_event(&mut self) -> Result<State, IntcodeError> {
+            Machine::run_until_event(self)
+        }
+
+        fn input(&mut self, val: i128) {
+            Machine::input(self, val)
+        }
+
+        fn state(&self) -> (usize, isize, Memory) {
+            (self.pc(), self.relative_base(), self.memory().clone())
+        }
+    }
+
+    impl Runner for CompiledMachine {
+        fn run_until_event(&mut self) -> Result<State, IntcodeError> {
+            CompiledMachine::run_until_event(self)
+        }
+
+        fn input(&mut self, val: i128) {
+            CompiledMachine::input(self, val)
+        }
+
+        fn state(&self) -> (usize, isize, Memory) {
+            (self.pc(), self.relative_base(), self.memory().clone())
+        }
+    }
+
+    // Outputs, final result and final state of machine
+    type Session = (
+        Vec<i128>,
+        Result<State, IntcodeError>,
+        (usize, isize, Memory),
+    );
+
+    // Runs machine until it stops, `feed` gets all outputs so far whenever
+    // input is needed and may end session returning `None`
+    fn run_session(
+        machine: &mut impl Runner,
+        mut feed: impl FnMut(&[i128]) -> Option<i128>,
+    ) -> Session {
+        let mut outputs = vec![];
+        let result = loop {
+            match machine.run_until_event() {
+                Ok(State::Output(val)) => outputs.push(val),
+                Ok(State::NeedsInput) => match feed(&outputs) {
+                    Some(val) => machine.input(val),
+                    None => break Ok(State::NeedsInput),
+                },
+                res => break res,
+            }
+        };
+
+        (outputs, result, machine.state())
+    }
+
+    // Compares interpreter and compiled program on interactive session
+    fn check_session<F: FnMut(&[i128]) -> Option<i128>>(program: Vec<i128>, feed: impl Fn() -> F) {
+        let expected = run_session(&mut Machine::new(program.clone()), feed());
+        let compiled = run_session(&mut Compiled::new(program).start(), feed());
+        assert_eq!(expected, compiled);
+    }
+
+    // Feeds values one by one, then finishes session
+    fn values(values: &[i128]) -> impl FnMut(&[i128]) -> Option<i128> + '_ {
+        let mut values = values.iter().copied();
+        move |_| values.next()
+    }
+
+    // Day 11 robot camera: color of panel under robot, painted by previous
+    // outputs. Session is finished after `limit` moves.
+    fn camera() -> impl FnMut(&[i128]) -> Option<i128> {
+        let (mut pos, mut dir) = ((0, 0), (0, -1));
+        let mut whites = HashSet::new();
+        let mut seen = 0;
+        move |outputs| {
+            for step in outputs[seen..].chunks(2) {
+                if let [color, turn] = step {
+                    if *color == 1 {
+                        whites.insert(pos);
+                    } else {
+                        whites.remove(&pos);
+                    }
+                    dir = if *turn == 0 {
+                        (dir.1, -dir.0)
+                    } else {
+                        (-dir.1, dir.0)
+                    };
+                    pos = (pos.0 + dir.0, pos.1 + dir.1);
+                }
+            }
+            seen = outputs.len() - outputs.len() % 2;
+            Some(whites.contains(&pos) as i128)
+        }
+    }
+
+    // Day 13 joystick, following ball with paddle
+    fn joystick(outputs: &[i128]) -> Option<i128> {
+        let (mut paddle, mut ball) = (0, 0);
+        for sprite in outputs.chunks(3) {
+            match sprite {
+                [x, _, 3] => paddle = *x,
+                [x, _, 4] => ball = *x,
+                _ => (),
+            }
+        }
+        Some((ball - paddle).signum())
+    }
+
+    // Checks puzzle input of given day the way the day runs it
+    fn check_day(day: u32, program: Vec<i128>) {
+        match day {
+            2 => {
+                let mut program = program;
+                program[1] = 12;
+                program[2] = 2;
+                check_session(program, || values(&[]));
+            }
+            5 => {
+                check_session(program.clone(), || values(&[1]));
+                check_session(program, || values(&[5]));
+            }
+            7 => {
+                check_session(program.clone(), || values(&[4, 0]));
+                check_session(program, || values(&[9, 0]));
+            }
+            9 => {
+                check_session(program.clone(), || values(&[1]));
+                check_session(program, || values(&[2]));
+            }
+            11 => check_session(program, camera),
+            13 => {
+                check_session(program.clone(), || values(&[]));
+                let mut program = program;
+                program[0] = 2;
+                check_session(program, || joystick);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn compile_test() {
+        // Day 2 example, patching its own code
+        let day2 = vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
+        check(day2.clone(), &[&[]]);
+        let mut machine = Compiled::new(day2).start();
+        assert_eq!(Ok(State::Halted), machine.run_until_event());
+        assert!(!machine.is_compiled());
+        assert_eq!(3500, machine.memory()[0]);
+
+        // Day 5 example, comparing input to 8
+        let day5 = vec![
+            3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0,
+            0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4,
+            20, 1105, 1, 46, 98, 99,
+        ];
+        check(day5, &[&[7], &[8], &[9], &[]]);
+
+        // Day 7 example amplifier
+        let day7 = vec![
+            3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+        ];
+        check(day7, &[&[4, 0], &[3, 4]]);
+
+        // Day 9 examples: quine and big numbers
+        let quine = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        let mut machine = Compiled::new(quine.clone()).start();
+        check(quine, &[&[]]);
+        while let Ok(State::Output(_)) = machine.run_until_event() {}
+        assert!(machine.is_compiled());
+        check(vec![1102, 34915192, 34915192, 7, 4, 7, 99, 0], &[&[]]);
+        check(vec![104, 1125899906842624, 99], &[&[]]);
+
+        // Failure inside compiled code
+        check(vec![109, -5, 204, 0, 99], &[&[]]);
+        check(vec![103, 0, 99], &[&[5]]);
+    }
+
+    #[test]
+    fn days_test() {
+        // Day 2 stand-in: noun and verb address cells to add, result is
+        // multiplied in place
+        check_day(2, vec![1, 0, 0, 0, 2, 0, 9, 0, 99, 3, 0, 0, 7]);
+
+        // Day 5 stand-in: example comparing input to 8
+        check_day(
+            5,
+            vec![
+                3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36,
+                98, 0, 0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000,
+                1, 20, 4, 20, 1105, 1, 46, 98, 99,
+            ],
+        );
+
+        // Day 7 stand-in: amplifier example computing phase * 10 + signal
+        check_day(
+            7,
+            vec![
+                3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+            ],
+        );
+
+        // Day 9 stand-in: input stored and echoed relative to moved base
+        check_day(9, vec![109, 10, 203, 0, 204, 0, 99]);
+
+        // Day 11 stand-in: robot brain painting panel white when it is black
+        // and turning right, or black and turning left otherwise. Stops
+        // after 100 panels.
+        let day11 = assemble(
+            "
+            loop:   READ [color]
+                    EQ [color], #0, [paint]
+                    WRT [paint]
+                    WRT [paint]
+                    ADD [steps], #-1, [steps]
+                    JMPT [steps], #loop
+                    EXIT
+            color:  DATA 0
+            paint:  DATA 0
+            steps:  DATA 100
+            ",
+        )
+        .unwrap();
+        check_day(11, day11);
+
+        // Day 13 stand-in: ball bouncing between walls at 0 and 9, paddle
+        // moved by joystick, score increased whenever paddle is under ball.
+        // Game ends when score reaches 20.
+        let day13 = assemble(
+            "
+                    MVB #stack
+            frame:  WRT [ball]
+                    WRT #5
+                    WRT #4
+                    WRT [paddle]
+                    WRT #6
+                    WRT #3
+                    READ [stick]
+                    ADD [paddle], [stick], [paddle]
+                    ADD [ball], [dir], [ball]
+                    EQ [ball], #0, rb
+                    JMPT rb, #bounce
+                    EQ [ball], #9, rb
+                    JMPF rb, #score
+            bounce: MUL [dir], #-1, [dir]
+            score:  EQ [ball], [paddle], rb
+                    ADD [points], rb, [points]
+                    WRT #-1
+                    WRT #0
+                    WRT [points]
+                    LESS [points], #20, rb
+                    JMPT rb, #frame
+                    EXIT
+            ball:   DATA 3
+            dir:    DATA 1
+            paddle: DATA 5
+            stick:  DATA 0
+            points: DATA 0
+            stack:  DATA 0
+            ",
+        )
+        .unwrap();
+        check_day(13, day13);
+    }
+
+    #[test]
+    #[ignore = "needs AOC_INPUTS"]
+    fn inputs_test() {
+        let dir = env::var(INPUTS_VAR).expect("AOC_INPUTS not set");
+
+        for &day in &[2, 5, 7, 9, 11, 13] {
+            let path = Path::new(&dir).join(format!("day{}.txt", day));
+            if path.exists() {
+                check_day(day, parse::load(&path).unwrap());
+            }
+        }
+    }
+}
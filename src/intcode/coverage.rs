@@ -0,0 +1,135 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// Memory addresses touched by `Machine` since coverage tracking was
+/// enabled. Writes from outside of program (`Machine::poke`) are not
+/// covered.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Coverage {
+    executed: BTreeSet<usize>,
+    read: BTreeSet<usize>,
+    written: BTreeSet<usize>,
+}
+
+// Splits addresses into ranges of consecutive addresses, as inclusive
+// (first, last) pairs
+fn ranges(addrs: &BTreeSet<usize>) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    for addr in addrs {
+        match ranges.last_mut() {
+            Some((_, last)) if *last + 1 == *addr => *last = *addr,
+            _ => ranges.push((*addr, *addr)),
+        }
+    }
+    ranges
+}
+
+impl Coverage {
+    pub(super) fn record_execute(&mut self, pc: usize, size: usize) {
+        // Last cell may be at the top address, so range is inclusive
+        self.executed.extend(pc..=pc + (size - 1));
+    }
+
+    pub(super) fn record_read(&mut self, addr: usize) {
+        self.read.insert(addr);
+    }
+
+    pub(super) fn record_write(&mut self, addr: usize) {
+        self.written.insert(addr);
+    }
+
+    /// Cells of executed instructions, including their arguments
+    pub fn executed(&self) -> &BTreeSet<usize> {
+        &self.executed
+    }
+
+    /// Cells read as instruction arguments in position or relative mode
+    pub fn read(&self) -> &BTreeSet<usize> {
+        &self.read
+    }
+
+    /// Cells written by program
+    pub fn written(&self) -> &BTreeSet<usize> {
+        &self.written
+    }
+
+    fn sections(&self) -> [(&'static str, Vec<(usize, usize)>); 3] {
+        [
+            ("executed", ranges(&self.executed)),
+            ("read", ranges(&self.read)),
+            ("written", ranges(&self.written)),
+        ]
+    }
+
+    /// Report as single JSON object, with ranges of addresses as inclusive
+    /// `[first, last]` pairs
+    pub fn to_json(&self) -> String {
+        let sections: Vec<_> = self
+            .sections()
+            .iter()
+            .map(|(name, ranges)| {
+                let ranges: Vec<_> = ranges
+                    .iter()
+                    .map(|(first, last)| format!("[{},{}]", first, last))
+                    .collect();
+                format!(r#""{}":[{}]"#, name, ranges.join(","))
+            })
+            .collect();
+
+        format!("{{{}}}", sections.join(","))
+    }
+}
+
+/// Text report, with single range of addresses per line, so reports of
+/// different runs are easy to diff
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, ranges) in &self.sections() {
+            for (first, last) in ranges {
+                writeln!(f, "{:8} {:6} - {}", name, first, last)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::{Machine, State};
+
+    #[test]
+    fn coverage_test() {
+        // Day 5 example: outputs 1 if input equals 8, 0 otherwise
+        let program = vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+        let mut machine: Machine = Machine::new(program);
+        machine.enable_coverage();
+        machine.input(8);
+        assert_eq!(Ok(State::Output(1)), machine.run_until_event());
+        assert_eq!(Ok(State::Halted), machine.run_until_event());
+
+        let coverage = machine.coverage().unwrap();
+        assert_eq!(
+            (0..9).collect::<Vec<_>>(),
+            coverage.executed().iter().copied().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![9, 10],
+            coverage.read().iter().copied().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![9],
+            coverage.written().iter().copied().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            "executed      0 - 8\n\
+             read          9 - 10\n\
+             written       9 - 9\n",
+            coverage.to_string()
+        );
+        assert_eq!(
+            r#"{"executed":[[0,8]],"read":[[9,10]],"written":[[9,9]]}"#,
+            coverage.to_json()
+        );
+    }
+}
@@ -0,0 +1,353 @@
+use super::disasm::decode_line;
+use super::{Machine, State};
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+use std::fmt::Write;
+
+const HELP: &str = "\
+s, step [N]         execute N instructions (default 1)
+u, undo [N]         step back N instructions (default 1)
+t, travel N         go to state after N instructions executed
+c, continue         run until breakpoint, halt, error or missing input
+b, break [ADDR]     set breakpoint on pc, list breakpoints if no address
+d, delete ADDR      remove breakpoint
+i, input VAL...     buffer values for program reads
+p, peek ADDR [N]    show N memory cells (default 1)
+w, poke ADDR VAL    overwrite memory cell
+l, list [ADDR] [N]  disassemble N instructions (default from pc, 10)
+r, regs             show pc, relative base, executed instructions and
+                    buffered input
+q, quit             exit debugger";
+
+// Number of most recent instructions which may be undone
+const UNDO_LIMIT: usize = 100_000;
+
+// Maximal number of cells or instructions shown at once
+const SHOW_LIMIT: i128 = 10_000;
+
+/// Interactive debugger over Intcode machine. Commands are executed one by
+/// one, every command returns text to be presented to user.
+pub struct Debugger {
+    machine: Machine,
+    breakpoints: BTreeSet<usize>,
+    halted: bool,
+}
+
+impl Debugger {
+    pub fn new(program: Vec<i128>) -> Self {
+        let mut machine = Machine::new(program);
+        machine.record_history(UNDO_LIMIT);
+
+        Self {
+            machine,
+            breakpoints: BTreeSet::new(),
+            halted: false,
+        }
+    }
+
+    pub fn machine(&self) -> &Machine {
+        &self.machine
+    }
+
+    /// Executes single command. Returns `None` if debugging session should
+    /// be finished.
+    pub fn execute(&mut self, command: &str) -> Option<String> {
+        let mut words = command.split_whitespace();
+        let cmd = match words.next() {
+            Some(cmd) => cmd,
+            None => return Some(String::new()),
+        };
+        let args: Result<Vec<i128>, _> = words.map(str::parse).collect();
+        let args = match args {
+            Ok(args) => args,
+            Err(err) => return Some(format!("Invalid argument: {}", err)),
+        };
+
+        let res = match (cmd, args.as_slice()) {
+            ("s", []) | ("step", []) => self.step(1),
+            ("s", [n]) | ("step", [n]) => self.step(*n),
+            ("u", []) | ("undo", []) => Ok(self.undo(1)),
+            ("u", [n]) | ("undo", [n]) => Ok(self.undo(*n)),
+            ("t", [n]) | ("travel", [n]) => self.travel(*n),
+            ("c", []) | ("continue", []) => self.cont(),
+            ("b", []) | ("break", []) => Ok(self.breakpoints()),
+            ("b", [addr]) | ("break", [addr]) => {
+                address(*addr).map(|addr| self.set_breakpoint(addr))
+            }
+            ("d", [addr]) | ("delete", [addr]) => {
+                address(*addr).map(|addr| self.delete_breakpoint(addr))
+            }
+            ("i", vals) | ("input", vals) if !vals.is_empty() => {
+                vals.iter().for_each(|v| self.machine.input(*v));
+                Ok(format!("{} values buffered", self.machine.pending_input()))
+            }
+            ("p", [addr]) | ("peek", [addr]) => address(*addr).and_then(|addr| self.peek(addr, 1)),
+            ("p", [addr, n]) | ("peek", [addr, n]) => {
+                address(*addr).and_then(|addr| self.peek(addr, *n))
+            }
+            ("w", [addr, val]) | ("poke", [addr, val]) => address(*addr).and_then(|addr| {
+                self.machine.poke(addr, *val);
+                self.peek(addr, 1)
+            }),
+            ("l", []) | ("list", []) => self.list(self.machine.pc(), 10),
+            ("l", [addr]) | ("list", [addr]) => address(*addr).and_then(|addr| self.list(addr, 10)),
+            ("l", [addr, n]) | ("list", [addr, n]) => {
+                address(*addr).and_then(|addr| self.list(addr, *n))
+            }
+            ("r", []) | ("regs", []) => Ok(self.regs()),
+            ("h", []) | ("help", []) => Ok(HELP.to_owned()),
+            ("q", []) | ("quit", []) => return None,
+            _ => Err(format!("Unknown command: {}, try `help`", command.trim())),
+        };
+
+        Some(res.unwrap_or_else(|err| err))
+    }
+
+    fn set_breakpoint(&mut self, addr: usize) -> String {
+        self.breakpoints.insert(addr);
+        format!("Breakpoint at {}", addr)
+    }
+
+    fn delete_breakpoint(&mut self, addr: usize) -> String {
+        if self.breakpoints.remove(&addr) {
+            format!("Breakpoint at {} removed", addr)
+        } else {
+            format!("No breakpoint at {}", addr)
+        }
+    }
+
+    fn breakpoints(&self) -> String {
+        let addrs: Vec<_> = self.breakpoints.iter().map(usize::to_string).collect();
+        format!("Breakpoints: {}", addrs.join(", "))
+    }
+
+    // Executes single instruction. Returns description of event which should
+    // stop execution, if any occurred.
+    fn single(&mut self, out: &mut String) -> Option<String> {
+        if self.halted {
+            return Some("Program halted".to_owned());
+        }
+
+        match self.machine.step() {
+            Ok(State::Running) => None,
+            Ok(State::Output(v)) => {
+                writeln!(out, "Output: {}", v).unwrap();
+                None
+            }
+            Ok(State::NeedsInput) => Some("Waiting for input".to_owned()),
+            Ok(State::Watchpoint(addr)) => Some(format!("Watchpoint on address {} hit", addr)),
+            Ok(State::Halted) => {
+                self.halted = true;
+                Some("Program halted".to_owned())
+            }
+            Err(err) => Some(format!("Error: {}", err)),
+        }
+    }
+
+    fn step(&mut self, n: i128) -> Result<String, String> {
+        let mut out = String::new();
+        for _ in 0..n {
+            if let Some(event) = self.single(&mut out) {
+                writeln!(out, "{}", event).unwrap();
+                break;
+            }
+        }
+
+        out += &self.current();
+        Ok(out)
+    }
+
+    fn undo(&mut self, n: i128) -> String {
+        let mut out = String::new();
+        for _ in 0..n {
+            if !self.machine.step_back() {
+                writeln!(out, "Beginning of history").unwrap();
+                break;
+            }
+            self.halted = false;
+        }
+
+        out += &self.current();
+        out
+    }
+
+    fn travel(&mut self, executed: i128) -> Result<String, String> {
+        let executed =
+            u64::try_from(executed).map_err(|_| format!("Invalid count: {}", executed))?;
+        match executed.checked_sub(self.machine.executed()) {
+            Some(forward) => self.step(forward as i128),
+            None if self.machine.travel_to(executed) => {
+                self.halted = false;
+                Ok(self.current())
+            }
+            None => Err(format!(
+                "State after {} instructions not recorded",
+                executed
+            )),
+        }
+    }
+
+    fn cont(&mut self) -> Result<String, String> {
+        let mut out = String::new();
+        loop {
+            if let Some(event) = self.single(&mut out) {
+                writeln!(out, "{}", event).unwrap();
+                break;
+            }
+
+            if self.breakpoints.contains(&self.machine.pc()) {
+                writeln!(out, "Breakpoint at {}", self.machine.pc()).unwrap();
+                break;
+            }
+        }
+
+        out += &self.current();
+        Ok(out)
+    }
+
+    fn current(&self) -> String {
+        decode_line(self.machine.memory(), self.machine.pc()).to_string()
+    }
+
+    fn peek(&self, addr: usize, n: i128) -> Result<String, String> {
+        if n <= 0 {
+            return Ok(String::new());
+        }
+        limit(n)?;
+
+        // Range is inclusive, so it may end at the last address
+        let last = usize::try_from(addr as i128 + n - 1)
+            .map_err(|_| format!("Invalid range: {} cells from {}", n, addr))?;
+        let cells: Vec<_> = (addr..=last)
+            .map(|addr| format!("{:4}: {}", addr, self.machine.peek(addr)))
+            .collect();
+        Ok(cells.join("\n"))
+    }
+
+    fn list(&self, mut addr: usize, n: i128) -> Result<String, String> {
+        limit(n)?;
+        let mut lines = vec![];
+        for _ in 0..n {
+            let line = decode_line(self.machine.memory(), addr);
+            let marker = if addr == self.machine.pc() { '*' } else { ' ' };
+            lines.push(format!("{}{}", marker, line));
+            addr = match addr.checked_add(line.size()) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+
+        Ok(lines.join("\n"))
+    }
+
+    fn regs(&self) -> String {
+        format!(
+            "pc: {}  relative_base: {}  executed: {}  buffered input: {}",
+            self.machine.pc(),
+            self.machine.relative_base(),
+            self.machine.executed(),
+            self.machine.pending_input()
+        )
+    }
+}
+
+fn limit(n: i128) -> Result<(), String> {
+    if n > SHOW_LIMIT {
+        Err(format!("Too many to show: {}, limit is {}", n, SHOW_LIMIT))
+    } else {
+        Ok(())
+    }
+}
+
+fn address(addr: i128) -> Result<usize, String> {
+    usize::try_from(addr).map_err(|_| format!("Invalid address: {}", addr))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Debugger;
+
+    #[test]
+    fn debugger_test() {
+        // Reads a value, and outputs it increased by 1 until it reaches 3
+        let mut dbg = Debugger::new(vec![
+            3, 16, 1001, 16, 1, 16, 4, 16, 1007, 16, 3, 17, 1005, 17, 2, 99,
+        ]);
+
+        assert_eq!(
+            Some("Waiting for input\n   0: [    3] READ  Pos(16)".to_owned()),
+            dbg.execute("c")
+        );
+        assert_eq!(Some("1 values buffered".to_owned()), dbg.execute("i 1"));
+        assert_eq!(Some("Breakpoint at 6".to_owned()), dbg.execute("b 6"));
+        assert_eq!(
+            Some("Breakpoint at 6\n   6: [    4] WRT   Pos(16)".to_owned()),
+            dbg.execute("c")
+        );
+        assert_eq!(Some("  16: 2\n  17: 0".to_owned()), dbg.execute("p 16 2"));
+        assert_eq!(
+            Some("Output: 2\n   8: [ 1007] LESS  Pos(16)  Imm(3)  Pos(17)".to_owned()),
+            dbg.execute("s")
+        );
+        assert_eq!(Some("  16: 0".to_owned()), dbg.execute("w 16 0"));
+        assert_eq!(
+            Some("Breakpoint at 6\n   6: [    4] WRT   Pos(16)".to_owned()),
+            dbg.execute("continue")
+        );
+        assert_eq!(
+            Some("Breakpoint at 6 removed".to_owned()),
+            dbg.execute("d 6")
+        );
+        assert_eq!(
+            Some("Output: 1\nOutput: 2\nOutput: 3\nProgram halted\n  15: [   99] EXIT".to_owned()),
+            dbg.execute("c")
+        );
+        assert_eq!(
+            Some("pc: 15  relative_base: 0  executed: 17  buffered input: 0".to_owned()),
+            dbg.execute("r")
+        );
+        assert_eq!(
+            Some("   12: [ 1005] JMPT  Pos(17)  Imm(2)\n*  15: [   99] EXIT".to_owned()),
+            dbg.execute("l 12 2")
+        );
+        assert_eq!(
+            Some("  12: [ 1005] JMPT  Pos(17)  Imm(2)".to_owned()),
+            dbg.execute("u")
+        );
+        assert_eq!(
+            Some("   2: [ 1001] ADD   Pos(16)  Imm(1)  Pos(16)".to_owned()),
+            dbg.execute("t 5")
+        );
+        assert_eq!(Some("  16: 0".to_owned()), dbg.execute("p 16"));
+        assert_eq!(
+            Some("Output: 1\n   2: [ 1001] ADD   Pos(16)  Imm(1)  Pos(16)".to_owned()),
+            dbg.execute("t 9")
+        );
+        assert_eq!(
+            Some("Beginning of history\n   0: [    3] READ  Pos(16)".to_owned()),
+            dbg.execute("u 10")
+        );
+        assert_eq!(
+            Some("Invalid range: 2 cells from 18446744073709551615".to_owned()),
+            dbg.execute("p 18446744073709551615 2")
+        );
+        assert_eq!(
+            Some("18446744073709551615: 0".to_owned()),
+            dbg.execute("p 18446744073709551615")
+        );
+        assert_eq!(
+            Some("Invalid address: 18446744073709551616".to_owned()),
+            dbg.execute("b 18446744073709551616")
+        );
+        assert_eq!(Some("Invalid address: -1".to_owned()), dbg.execute("p -1"));
+        assert_eq!(
+            Some("Too many to show: 100000000000000, limit is 10000".to_owned()),
+            dbg.execute("p 0 100000000000000")
+        );
+        assert_eq!(
+            Some("Too many to show: 10001, limit is 10000".to_owned()),
+            dbg.execute("l 0 10001")
+        );
+        assert_eq!(None, dbg.execute("q"));
+    }
+}
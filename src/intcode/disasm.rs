@@ -0,0 +1,280 @@
+use super::memory::Cells;
+use super::{decode, Argument, Op};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// Single entry of disassembled program
+#[derive(Debug, Clone, PartialEq)]
+pub enum Line {
+    /// Decoded instruction; `args` contains only arguments taken by `op`
+    Instruction {
+        addr: usize,
+        raw: i128,
+        op: Op,
+        args: Vec<Argument>,
+    },
+    /// Termination opcode
+    Exit { addr: usize },
+    /// Value not recognized as a part of code
+    Data { addr: usize, value: i128 },
+}
+
+impl Line {
+    pub fn addr(&self) -> usize {
+        match self {
+            Self::Instruction { addr, .. } | Self::Exit { addr } | Self::Data { addr, .. } => *addr,
+        }
+    }
+
+    /// Number of memory cells covered by line
+    pub fn size(&self) -> usize {
+        match self {
+            Self::Instruction { args, .. } => args.len() + 1,
+            Self::Exit { .. } | Self::Data { .. } => 1,
+        }
+    }
+}
+
+/// Disassembled program
+pub struct Listing {
+    lines: Vec<Line>,
+    targets: BTreeSet<usize>,
+}
+
+impl Listing {
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    /// Checks if address is known jump target or entry point
+    pub fn is_target(&self, addr: usize) -> bool {
+        self.targets.contains(&addr)
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Instruction {
+                addr,
+                raw,
+                op,
+                args,
+                ..
+            } => {
+                let args: Vec<_> = args.iter().map(|arg| format!("{:?}", arg)).collect();
+                write!(
+                    f,
+                    "{:4}: [{:5}] {:5} {}",
+                    addr,
+                    raw,
+                    op.mnemonic(),
+                    args.join("  ")
+                )
+            }
+            Self::Exit { addr } => write!(f, "{:4}: [{:5}] EXIT", addr, 99),
+            Self::Data { addr, value } => write!(f, "{:4}: [{:5}] DATA", addr, value),
+        }
+    }
+}
+
+impl fmt::Display for Listing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.lines {
+            let marker = if self.is_target(line.addr()) {
+                '>'
+            } else {
+                ' '
+            };
+            writeln!(f, "{}{}", marker, line)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Decodes single line at given address, without any code/data analysis -
+/// anything what decodes properly is considered an instruction
+pub fn decode_line(program: &(impl Cells + ?Sized), addr: usize) -> Line {
+    let raw = program.cell(addr);
+    if raw == 99 {
+        return Line::Exit { addr };
+    }
+
+    match decode(program, addr) {
+        Ok((op, args)) => Line::Instruction {
+            addr,
+            raw,
+            op,
+            args: args[..op.args()].to_vec(),
+        },
+        Err(_) => Line::Data { addr, value: raw },
+    }
+}
+
+// Static reachability analysis. Returns addresses of reachable instructions
+// and known jump targets.
+//
+// Code is followed from entry points through fallthroughs and jumps to
+// immediate addresses. Jumps to positional or relative addresses cannot be
+// resolved statically, but in practice they are mostly returns from
+// subroutines, and return addresses are pushed as immediates just before
+// the call. Because of that any address right after an unconditional jump,
+// which also appears as an immediate operand somewhere in reachable code, is
+// considered an entry point too.
+fn analyze(
+    program: &[i128],
+    entries: impl IntoIterator<Item = usize>,
+) -> (BTreeSet<usize>, BTreeSet<usize>) {
+    let mut code = BTreeSet::new();
+    let mut targets: BTreeSet<_> = entries.into_iter().collect();
+    let mut immediates = BTreeSet::new();
+    let mut after_jumps = BTreeSet::new();
+    let mut pending: Vec<_> = targets.iter().copied().collect();
+
+    loop {
+        while let Some(pc) = pending.pop() {
+            if pc >= program.len() || code.contains(&pc) {
+                continue;
+            }
+
+            if program[pc] == 99 {
+                code.insert(pc);
+                continue;
+            }
+
+            let (op, args) = match decode(program, pc) {
+                Ok(decoded) => decoded,
+                Err(_) => continue,
+            };
+            let next = pc + op.args() + 1;
+            if next > program.len() {
+                continue;
+            }
+            code.insert(pc);
+
+            for arg in &args[..op.args()] {
+                if let Argument::Imm(v) = arg {
+                    if *v >= 0 {
+                        immediates.insert(*v as usize);
+                    }
+                }
+            }
+
+            let (taken, fallthrough) = match (op, args[0]) {
+                (Op::JmpT, Argument::Imm(c)) => (c != 0, c == 0),
+                (Op::JmpF, Argument::Imm(c)) => (c == 0, c != 0),
+                (Op::JmpT, _) | (Op::JmpF, _) => (true, true),
+                _ => (false, true),
+            };
+
+            if taken {
+                if let Argument::Imm(target) = args[1] {
+                    if target >= 0 {
+                        targets.insert(target as usize);
+                        pending.push(target as usize);
+                    }
+                }
+            }
+
+            if fallthrough {
+                pending.push(next);
+            } else {
+                after_jumps.insert(next);
+            }
+        }
+
+        pending = after_jumps
+            .intersection(&immediates)
+            .filter(|addr| !code.contains(addr))
+            .copied()
+            .collect();
+
+        if pending.is_empty() {
+            break;
+        }
+
+        targets.extend(pending.iter().copied());
+    }
+
+    (code, targets)
+}
+
+/// Disassembles program, assuming that its only entry point is address 0
+pub fn disassemble(program: &[i128]) -> Listing {
+    disassemble_from(program, Some(0))
+}
+
+/// Disassembles program, following code from all given entry points
+pub fn disassemble_from(program: &[i128], entries: impl IntoIterator<Item = usize>) -> Listing {
+    let (code, targets) = analyze(program, entries);
+
+    let mut lines = vec![];
+    let mut addr = 0;
+    while addr < program.len() {
+        let line = if code.contains(&addr) {
+            decode_line(program, addr)
+        } else {
+            Line::Data {
+                addr,
+                value: program[addr],
+            }
+        };
+
+        addr += line.size();
+        lines.push(line);
+    }
+
+    Listing { lines, targets }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{disassemble, Line};
+    use crate::intcode::{Argument, Op};
+
+    #[test]
+    fn disassemble_test() {
+        let listing = disassemble(&[1105, 1, 4, 42, 104, 7, 99, 5]);
+        assert_eq!(
+            &[
+                Line::Instruction {
+                    addr: 0,
+                    raw: 1105,
+                    op: Op::JmpT,
+                    args: vec![Argument::Imm(1), Argument::Imm(4)],
+                },
+                Line::Data { addr: 3, value: 42 },
+                Line::Instruction {
+                    addr: 4,
+                    raw: 104,
+                    op: Op::Write,
+                    args: vec![Argument::Imm(7)],
+                },
+                Line::Exit { addr: 6 },
+                Line::Data { addr: 7, value: 5 },
+            ],
+            listing.lines()
+        );
+        assert!(listing.is_target(4));
+        assert!(!listing.is_target(6));
+    }
+
+    #[test]
+    fn return_address_test() {
+        let listing = disassemble(&[1101, 7, 0, 20, 1105, 1, 8, 99, 6, 21, 20, 99]);
+        let addrs: Vec<_> = listing
+            .lines()
+            .iter()
+            .filter(|line| !matches!(line, Line::Data { .. }))
+            .map(Line::addr)
+            .collect();
+
+        assert_eq!(vec![0, 4, 7, 8, 11], addrs);
+        assert!(listing.is_target(7));
+        assert_eq!(
+            Some(">   0: [ 1101] ADD   Imm(7)  Imm(0)  Pos(20)"),
+            listing.to_string().lines().next()
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::VecDeque;
+
+/// Machine state overwritten by single instruction
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Entry<W> {
+    pub(super) pc: usize,
+    pub(super) relative_base: isize,
+    /// Written address with its previous value
+    pub(super) write: Option<(usize, W)>,
+    /// Input value consumed by instruction
+    pub(super) input: Option<W>,
+    /// Cells overwritten by `Machine::poke` after instruction, with their
+    /// previous values
+    pub(super) pokes: Vec<(usize, W)>,
+}
+
+/// Undo log of executed instructions, oldest first. Only `limit` most
+/// recent instructions are kept.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(super) struct History<W> {
+    entries: VecDeque<Entry<W>>,
+    limit: usize,
+}
+
+impl<W> History<W> {
+    pub(super) fn new(limit: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            limit,
+        }
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub(super) fn push(&mut self, entry: Entry<W>) {
+        if self.limit == 0 {
+            return;
+        }
+        if self.entries.len() == self.limit {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// Pokes before first recorded instruction are never undone
+    pub(super) fn poke(&mut self, addr: usize, old: W) {
+        if let Some(entry) = self.entries.back_mut() {
+            entry.pokes.push((addr, old));
+        }
+    }
+
+    pub(super) fn pop(&mut self) -> Option<Entry<W>> {
+        self.entries.pop_back()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::watch::{Access, Action};
+    use crate::intcode::{Machine, State};
+
+    #[test]
+    fn history_test() {
+        // Day 9 quine, outputs its own code using relative base
+        let program = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        let mut machine: Machine = Machine::new(program.clone());
+        assert!(!machine.step_back());
+        machine.record_history(1000);
+
+        let mut outputs = vec![];
+        while let Ok(State::Output(val)) = machine.run_until_event() {
+            outputs.push(val);
+        }
+        assert_eq!(program, outputs);
+        assert_eq!(80, machine.executed());
+        assert_eq!(80, machine.history_len());
+
+        // Back to state right after first output
+        assert!(machine.travel_to(2));
+        assert_eq!(
+            (4, 1, 2),
+            (machine.pc(), machine.relative_base(), machine.executed())
+        );
+        assert_eq!(0, machine.peek(100));
+        assert_eq!(Ok(State::Running), machine.step());
+        assert_eq!(1, machine.peek(100));
+
+        // Replaying gives the same results
+        let mut outputs = vec![109];
+        while let Ok(State::Output(val)) = machine.run_until_event() {
+            outputs.push(val);
+        }
+        assert_eq!(program, outputs);
+
+        assert!(machine.travel_to(0));
+        assert!(!machine.step_back());
+        assert_eq!(program[..], machine.memory().image()[..program.len()]);
+        assert_eq!((0, 0), (machine.peek(100), machine.peek(101)));
+        assert_eq!((0, 0), (machine.pc(), machine.relative_base()));
+
+        // Consumed input is given back
+        let mut machine: Machine = Machine::new(vec![3, 5, 4, 5, 99, 0]);
+        machine.record_history(1000);
+        machine.input(7);
+        assert_eq!(Ok(State::Output(7)), machine.run_until_event());
+        assert!(machine.travel_to(0));
+        assert_eq!(1, machine.pending_input());
+        assert_eq!(0, machine.peek(5));
+
+        // Pokes are undone together with preceding instruction
+        machine.poke(5, 1);
+        machine.input(2);
+        assert_eq!(Ok(State::Running), machine.step());
+        machine.poke(5, 3);
+        machine.poke(5, 4);
+        assert!(machine.step_back());
+        assert_eq!(1, machine.peek(5));
+
+        // Watchpoint stop of undone instruction is not reported
+        let mut machine: Machine = Machine::new(vec![3, 5, 4, 5, 99, 0]);
+        machine.record_history(1000);
+        machine.watch(5..6, Access::Read, Box::new(|_| Action::Stop));
+        machine.input(7);
+        assert_eq!(Ok(State::Output(7)), machine.run_until_event());
+        assert!(machine.step_back());
+        assert_eq!(Ok(State::Output(7)), machine.step());
+        assert_eq!(Ok(State::Watchpoint(5)), machine.step());
+        assert_eq!(Ok(State::Halted), machine.run_until_event());
+    }
+
+    #[test]
+    fn limit_test() {
+        // Outputs sum of every two inputs
+        let program = vec![3, 13, 3, 14, 1, 13, 14, 15, 4, 15, 1105, 1, 0];
+        let mut machine: Machine = Machine::new(program.clone());
+        machine.record_history(3);
+        machine.record_transcript();
+        machine.enable_profiling();
+        machine.enable_coverage();
+        (1..=4).for_each(|val| machine.input(val));
+
+        let mut outputs = vec![];
+        while let Ok(State::Output(val)) = machine.run_until_event() {
+            outputs.push(val);
+        }
+        assert_eq!(vec![3, 7], outputs);
+        assert_eq!(10, machine.executed());
+        assert_eq!(3, machine.history_len());
+        assert!(!machine.travel_to(6));
+        let transcript = machine.transcript().unwrap().clone();
+
+        // Undoing output and following jump rewinds transcript, statistics
+        // cannot be rewound so they are discarded
+        assert!(machine.travel_to(8));
+        assert_eq!(1, machine.history_len());
+        assert_eq!(5, machine.transcript().unwrap().events.len());
+        assert!(machine.profile().is_none());
+        assert!(machine.coverage().is_none());
+
+        assert_eq!(Ok(State::Output(7)), machine.run_until_event());
+        assert_eq!(Ok(State::NeedsInput), machine.run_until_event());
+        assert_eq!(Some(&transcript), machine.transcript());
+    }
+}
@@ -0,0 +1,143 @@
+use super::word::Word;
+use super::{ErrorKind, Machine};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+// Checking clock on every instruction would be too expensive
+const CLOCK_CHECK_INTERVAL: u64 = 1024;
+
+/// Execution limits of machine. All limits are disabled by default.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Limits {
+    /// Maximum number of executed instructions
+    pub max_instructions: Option<u64>,
+    /// Maximum wall-clock time of execution, measured from setting limits
+    pub timeout: Option<Duration>,
+    /// Fail when machine revisits identical (pc, relative_base, memory)
+    /// state without consuming any input in between. States are compared by
+    /// their hashes, and every instruction hashes whole memory, so it is
+    /// expensive.
+    pub detect_loops: bool,
+}
+
+pub(super) struct Watchdog {
+    limits: Limits,
+    deadline: Option<Instant>,
+    // Hashes of states visited since last input consumption
+    visited: HashSet<u64>,
+}
+
+impl Watchdog {
+    pub(super) fn new(limits: Limits) -> Self {
+        Self {
+            deadline: limits.timeout.map(|timeout| Instant::now() + timeout),
+            limits,
+            visited: HashSet::new(),
+        }
+    }
+
+    /// Called before any instruction is executed
+    // `is_multiple_of` would raise minimal supported Rust version
+    #[allow(clippy::manual_is_multiple_of)]
+    pub(super) fn check<W: Word>(&mut self, machine: &Machine<W>) -> Result<(), ErrorKind> {
+        let executed = machine.executed();
+
+        if let Some(max) = self.limits.max_instructions {
+            if executed >= max {
+                return Err(ErrorKind::InstructionLimit(max));
+            }
+        }
+
+        if let (Some(deadline), Some(timeout)) = (self.deadline, self.limits.timeout) {
+            if executed % CLOCK_CHECK_INTERVAL == 0 && Instant::now() >= deadline {
+                return Err(ErrorKind::Timeout(timeout));
+            }
+        }
+
+        if self.limits.detect_loops {
+            let mut hasher = DefaultHasher::new();
+            (machine.pc, machine.relative_base, &machine.memory).hash(&mut hasher);
+            if !self.visited.insert(hasher.finish()) {
+                return Err(ErrorKind::InfiniteLoop);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn input_consumed(&mut self) {
+        self.visited.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Limits;
+    use crate::intcode::{ErrorKind, Machine, State};
+    use std::time::Duration;
+
+    fn failure(program: Vec<i128>, limits: Limits) -> ErrorKind {
+        let mut machine = Machine::new(program);
+        machine.set_limits(limits);
+        machine.input(0);
+        loop {
+            match machine.run_until_event() {
+                Ok(State::Output(_)) => (),
+                Ok(state) => panic!("Unexpected state: {:?}", state),
+                Err(err) => return err.kind,
+            }
+        }
+    }
+
+    #[test]
+    fn limits_test() {
+        // Counts up, printing every value
+        let counter = vec![1001, 9, 1, 9, 4, 9, 1105, 1, 0];
+        // Reads input, and then loops forever
+        let spin = vec![3, 5, 1105, 1, 2, 0];
+
+        assert_eq!(
+            ErrorKind::InstructionLimit(100),
+            failure(
+                counter.clone(),
+                Limits {
+                    max_instructions: Some(100),
+                    ..Limits::default()
+                }
+            )
+        );
+        assert_eq!(
+            ErrorKind::Timeout(Duration::from_secs(0)),
+            failure(
+                counter.clone(),
+                Limits {
+                    timeout: Some(Duration::from_secs(0)),
+                    ..Limits::default()
+                }
+            )
+        );
+        assert_eq!(
+            ErrorKind::InfiniteLoop,
+            failure(
+                spin,
+                Limits {
+                    detect_loops: true,
+                    ..Limits::default()
+                }
+            )
+        );
+        assert_eq!(
+            ErrorKind::InstructionLimit(1000),
+            failure(
+                counter,
+                Limits {
+                    max_instructions: Some(1000),
+                    detect_loops: true,
+                    ..Limits::default()
+                }
+            )
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use super::word::Word;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+use std::ops::Index;
+
+/// Number of cells in single page of sparse memory
+pub const PAGE_SIZE: usize = 1024;
+
+// Memory below this address is kept contiguous even if program image is
+// smaller, most programs use some space right after their image
+const DENSE_SIZE: usize = 64 * 1024;
+
+/// Read-only access to memory cells, implemented both for plain program
+/// images and for machine memory. Cells never written are 0.
+pub trait Cells<W = i128> {
+    fn cell(&self, addr: usize) -> W;
+}
+
+impl<W: Word> Cells<W> for [W] {
+    fn cell(&self, addr: usize) -> W {
+        self.get(addr).cloned().unwrap_or_else(|| W::from_i64(0))
+    }
+}
+
+/// Machine memory. Program image and addresses right after it are stored
+/// in contiguous vector, far addresses are stored in sparse pages allocated
+/// on first write, so writing to distant address is cheap.
+///
+/// Memories are compared by content, no matter how cells are allocated.
+#[derive(Debug, Clone)]
+pub struct Memory<W = i128> {
+    dense: Vec<W>,
+    // Dense part never grows beyond this address, all cells above it are
+    // paged
+    dense_limit: usize,
+    // Pages by their index (address / PAGE_SIZE)
+    pages: BTreeMap<usize, Box<[W]>>,
+    // Referenced by cells never written
+    zero: W,
+}
+
+impl<W: Word> Memory<W> {
+    pub fn new(program: Vec<W>) -> Self {
+        Self {
+            dense_limit: program.len().max(DENSE_SIZE),
+            dense: program,
+            pages: BTreeMap::new(),
+            zero: W::from_i64(0),
+        }
+    }
+
+    pub fn get(&self, addr: usize) -> W {
+        self[addr].clone()
+    }
+
+    pub fn set(&mut self, addr: usize, val: W) {
+        if addr < self.dense_limit {
+            if addr >= self.dense.len() {
+                self.dense.resize(addr + 1, self.zero.clone());
+            }
+            self.dense[addr] = val;
+            return;
+        }
+
+        let idx = addr / PAGE_SIZE;
+        if val.is_zero() && !self.pages.contains_key(&idx) {
+            return;
+        }
+
+        let zero = &self.zero;
+        self.pages
+            .entry(idx)
+            .or_insert_with(|| vec![zero.clone(); PAGE_SIZE].into_boxed_slice())
+            [addr % PAGE_SIZE] = val;
+    }
+
+    /// Contiguous part of memory, starting at address 0. It covers at least
+    /// whole program image.
+    pub fn image(&self) -> &[W] {
+        &self.dense
+    }
+
+    /// Allocated pages beyond contiguous part, as (first address, cells),
+    /// in address order
+    pub fn pages(&self) -> impl Iterator<Item = (usize, &[W])> {
+        self.pages
+            .iter()
+            .map(|(idx, page)| (idx * PAGE_SIZE, page.as_ref()))
+    }
+
+    /// Number of cells actually allocated, both contiguous and paged
+    pub fn allocated(&self) -> usize {
+        self.dense.len() + self.pages.len() * PAGE_SIZE
+    }
+}
+
+impl<W: PartialEq> Memory<W> {
+    // Non-zero cells with their addresses, in address order
+    fn non_zero(&self) -> impl Iterator<Item = (usize, &W)> {
+        let dense = self.dense.iter().enumerate();
+        let pages = self.pages.iter().flat_map(|(idx, page)| {
+            page.iter()
+                .enumerate()
+                .map(move |(offset, val)| (idx * PAGE_SIZE + offset, val))
+        });
+        dense
+            .chain(pages)
+            .filter(move |(_, val)| *val != &self.zero)
+    }
+}
+
+impl<W: PartialEq> PartialEq for Memory<W> {
+    fn eq(&self, other: &Self) -> bool {
+        self.non_zero().eq(other.non_zero())
+    }
+}
+
+impl<W: PartialEq + Hash> Hash for Memory<W> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.non_zero().for_each(|cell| cell.hash(state));
+    }
+}
+
+impl<W: Word> Cells<W> for Memory<W> {
+    fn cell(&self, addr: usize) -> W {
+        self.get(addr)
+    }
+}
+
+impl<W> Index<usize> for Memory<W> {
+    type Output = W;
+
+    fn index(&self, addr: usize) -> &W {
+        if addr < self.dense_limit {
+            return self.dense.get(addr).unwrap_or(&self.zero);
+        }
+
+        self.pages
+            .get(&(addr / PAGE_SIZE))
+            .map_or(&self.zero, |page| &page[addr % PAGE_SIZE])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Memory, PAGE_SIZE};
+
+    #[test]
+    fn memory_test() {
+        let mut memory: Memory = Memory::new(vec![1, 2, 3]);
+        memory.set(10, 4);
+        memory.set(1 << 40, 5);
+        memory.set(1 << 50, 0);
+
+        assert_eq!(2, memory[1]);
+        assert_eq!(4, memory.get(10));
+        assert_eq!(0, memory.get(9));
+        assert_eq!(5, memory[1 << 40]);
+        assert_eq!(0, memory.get((1 << 40) + 1));
+        assert_eq!(11, memory.image().len());
+
+        let pages: Vec<_> = memory
+            .pages()
+            .map(|(addr, page)| (addr, page.len()))
+            .collect();
+        assert_eq!(vec![(1 << 40, PAGE_SIZE)], pages);
+
+        // Zeroed page and grown contiguous part don't change content
+        let mut zeroed = memory.clone();
+        zeroed.set(1 << 50, 6);
+        zeroed.set(1 << 50, 0);
+        zeroed.set(100, 0);
+        assert_eq!(memory, zeroed);
+        zeroed.set(1 << 50, 6);
+        assert_ne!(memory, zeroed);
+    }
+}
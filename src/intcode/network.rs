@@ -0,0 +1,266 @@
+use super::word::Word;
+use super::{address, IntcodeError, Machine, State};
+use std::fmt;
+
+/// Address of NAT, packets sent to it are passed to `Nat::receive`
+pub const NAT_ADDRESS: usize = 255;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Packet<W = i128> {
+    pub dest: usize,
+    pub x: W,
+    pub y: W,
+}
+
+/// Decision of NAT about further network operation
+#[derive(Debug, Clone, PartialEq)]
+pub enum Control<W = i128> {
+    Continue,
+    /// Sends packet on behalf of NAT
+    Send(Packet<W>),
+    /// Finishes `Network::run`
+    Stop,
+}
+
+/// Monitor of network, receiving packets sent to `NAT_ADDRESS`
+pub trait Nat<W = i128> {
+    fn receive(&mut self, packet: Packet<W>) -> Control<W>;
+
+    /// Called when no machine received nor sent any packet for whole round.
+    /// Returning `Control::Continue` fails the network with
+    /// `NetworkError::Deadlock`, as nothing can wake it up anymore.
+    fn idle(&mut self) -> Control<W>;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetworkError {
+    /// Machine with given address failed
+    Machine(usize, IntcodeError),
+    /// Packet sent to address without machine
+    InvalidAddress(i128),
+    /// Network is idle and NAT didn't wake it up
+    Deadlock,
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Machine(addr, err) => write!(f, "machine {} failed: {}", addr, err),
+            Self::InvalidAddress(addr) => write!(f, "packet sent to invalid address {}", addr),
+            Self::Deadlock => write!(f, "network idle forever"),
+        }
+    }
+}
+
+impl std::error::Error for NetworkError {}
+
+struct Node<W> {
+    machine: Machine<W>,
+    // Output values not forming complete packet yet
+    output: Vec<W>,
+    halted: bool,
+}
+
+/// Cluster of machines running the same program, exchanging packets. Every
+/// machine receives its address as first input, then sends packets as
+/// (destination, X, Y) output triples. Packets are appended to input of
+/// destination machine, machine reading with no input gets -1.
+///
+/// Machines are scheduled round-robin, every machine runs until it reads -1
+/// for the second time in its turn.
+pub struct Network<W = i128> {
+    nodes: Vec<Node<W>>,
+}
+
+impl<W: Word> Network<W> {
+    /// Boots `size` machines, with addresses from 0 to `size - 1`
+    pub fn new(program: Vec<W>, size: usize) -> Self {
+        let nodes = (0..size)
+            .map(|addr| {
+                let mut machine = Machine::new(program.clone());
+                machine.input(W::from_i64(addr as i64));
+                Node {
+                    machine,
+                    output: vec![],
+                    halted: false,
+                }
+            })
+            .collect();
+
+        Self { nodes }
+    }
+
+    pub fn machine(&self, addr: usize) -> &Machine<W> {
+        &self.nodes[addr].machine
+    }
+
+    /// Runs network until NAT stops it or every machine halts
+    pub fn run(&mut self, nat: &mut impl Nat<W>) -> Result<(), NetworkError> {
+        loop {
+            let mut idle = true;
+            for addr in 0..self.nodes.len() {
+                let (packets, busy) = self.turn(addr)?;
+                idle &= !busy;
+
+                for packet in packets {
+                    if self.deliver(packet, nat)? {
+                        return Ok(());
+                    }
+                }
+            }
+
+            if self.nodes.iter().all(|node| node.halted) {
+                return Ok(());
+            }
+
+            if idle {
+                let packet = match nat.idle() {
+                    Control::Continue => return Err(NetworkError::Deadlock),
+                    Control::Stop => return Ok(()),
+                    Control::Send(packet) => packet,
+                };
+
+                if self.deliver(packet, nat)? {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    // Runs single machine for its turn. Returns packets it sent, and if it
+    // did anything but waiting for packets.
+    fn turn(&mut self, addr: usize) -> Result<(Vec<Packet<W>>, bool), NetworkError> {
+        let node = &mut self.nodes[addr];
+        let received = node.machine.pending_input() > 0;
+        let mut polled = false;
+        let mut packets = vec![];
+
+        while !node.halted {
+            match node.machine.run_until_event() {
+                Ok(State::Output(val)) => {
+                    node.output.push(val);
+                    if let [dest, x, y] = node.output.as_slice() {
+                        let dest = address(dest)
+                            .map_err(|_| NetworkError::InvalidAddress(dest.clamp_i128()))?;
+                        packets.push(Packet {
+                            dest,
+                            x: x.clone(),
+                            y: y.clone(),
+                        });
+                        node.output.clear();
+                    }
+                }
+                Ok(State::NeedsInput) if polled => break,
+                Ok(State::NeedsInput) => {
+                    polled = true;
+                    node.machine.input(W::from_i64(-1));
+                }
+                // Network machines have no watchpoints
+                Ok(State::Running) | Ok(State::Watchpoint(_)) => (),
+                Ok(State::Halted) => node.halted = true,
+                Err(err) => return Err(NetworkError::Machine(addr, err)),
+            }
+        }
+
+        let busy = received || !packets.is_empty();
+        Ok((packets, busy))
+    }
+
+    // Delivers packet to its destination. Returns if NAT stopped network.
+    fn deliver(
+        &mut self,
+        mut packet: Packet<W>,
+        nat: &mut impl Nat<W>,
+    ) -> Result<bool, NetworkError> {
+        while packet.dest == NAT_ADDRESS {
+            packet = match nat.receive(packet) {
+                Control::Continue => return Ok(false),
+                Control::Stop => return Ok(true),
+                Control::Send(packet) => packet,
+            };
+        }
+
+        let node = self
+            .nodes
+            .get_mut(packet.dest)
+            .ok_or(NetworkError::InvalidAddress(packet.dest as i128))?;
+        node.machine.input(packet.x);
+        node.machine.input(packet.y);
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Control, Nat, Network, NetworkError, Packet, NAT_ADDRESS};
+    use crate::intcode::asm::assemble;
+
+    // Resends last received packet to machine 0 whenever network is idle,
+    // until Y reaches 9
+    #[derive(Default)]
+    struct Relay {
+        last: Option<Packet>,
+        received: Vec<i128>,
+    }
+
+    impl Nat for Relay {
+        fn receive(&mut self, packet: Packet) -> Control {
+            self.received.push(packet.y);
+            if packet.y >= 9 {
+                return Control::Stop;
+            }
+            self.last = Some(packet);
+            Control::Continue
+        }
+
+        fn idle(&mut self) -> Control {
+            let (x, y) = self.last.take().map_or((7, 0), |p| (p.x, p.y));
+            Control::Send(Packet { dest: 0, x, y })
+        }
+    }
+
+    #[test]
+    fn network_test() {
+        // Passes every packet to next machine with Y increased by 1, last of
+        // three machines sends to NAT
+        let program = assemble(
+            "
+                    READ [next]
+                    ADD [next], #1, [next]
+                    EQ [next], #3, [last]
+                    JMPF [last], #loop
+                    ADD #255, #0, [next]
+            loop:   READ [x]
+                    EQ [x], #-1, [tmp]
+                    JMPT [tmp], #loop
+                    READ [y]
+                    ADD [y], #1, [y]
+                    WRT [next]
+                    WRT [x]
+                    WRT [y]
+                    JMPT #1, #loop
+            next:   DATA 0
+            last:   DATA 0
+            x:      DATA 0
+            y:      DATA 0
+            tmp:    DATA 0
+            ",
+        )
+        .unwrap();
+
+        let mut network = Network::new(program.clone(), 3);
+        let mut relay = Relay::default();
+        assert_eq!(Ok(()), network.run(&mut relay));
+        assert_eq!(vec![3, 6, 9], relay.received);
+        assert_eq!(
+            NAT_ADDRESS as i128,
+            network.machine(2).peek(program.len() - 5)
+        );
+
+        let mut network = Network::new(program, 2);
+        assert_eq!(
+            Err(NetworkError::InvalidAddress(2)),
+            network.run(&mut Relay::default())
+        );
+    }
+}
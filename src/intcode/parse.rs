@@ -0,0 +1,184 @@
+use super::word::Word;
+use async_std::prelude::*;
+use async_std::stream::Stream;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    /// Token which is not a valid value
+    InvalidValue(String),
+    /// Comma not preceded by any value
+    MissingValue,
+    /// No values at all
+    EmptyProgram,
+}
+
+/// Program parsing failure, with position (counted from 1) where it occurred
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match &self.kind {
+            ParseErrorKind::InvalidValue(token) => write!(f, "invalid value `{}`", token),
+            ParseErrorKind::MissingValue => write!(f, "missing value"),
+            ParseErrorKind::EmptyProgram => write!(f, "empty program"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses program from text. Values are separated with commas, whitespace or
+/// line breaks; trailing comma is allowed.
+pub fn parse<W: Word>(text: &str) -> Result<Vec<W>, ParseError> {
+    let mut program = vec![];
+    // Comma was the last separator seen, so value is expected
+    let mut after_comma = false;
+
+    for (line_idx, line) in text.lines().enumerate() {
+        let error = |column, kind| ParseError {
+            line: line_idx + 1,
+            column,
+            kind,
+        };
+        // (column, byte offset) of current token
+        let mut token = None;
+
+        // Extra space terminates the last token of line
+        let chars = line.char_indices().chain(Some((line.len(), ' ')));
+        for (column, (offset, c)) in (1..).zip(chars) {
+            if c != ',' && !c.is_whitespace() {
+                token = token.or(Some((column, offset)));
+                continue;
+            }
+
+            if let Some((column, start)) = token.take() {
+                let token = &line[start..offset];
+                let val = token
+                    .parse()
+                    .map_err(|_| error(column, ParseErrorKind::InvalidValue(token.to_owned())))?;
+                program.push(val);
+                after_comma = false;
+            } else if c == ',' && (after_comma || program.is_empty()) {
+                return Err(error(column, ParseErrorKind::MissingValue));
+            }
+
+            after_comma |= c == ',';
+        }
+    }
+
+    if program.is_empty() {
+        return Err(ParseError {
+            line: 1,
+            column: 1,
+            kind: ParseErrorKind::EmptyProgram,
+        });
+    }
+
+    Ok(program)
+}
+
+/// Reads program from stream of lines. Program ends with first blank line
+/// following it, or with end of stream, so lines after the blank one are
+/// left in stream. Blank lines before program are skipped.
+pub async fn read<W: Word, S: Stream<Item = String> + Unpin>(
+    input: &mut S,
+) -> Result<Vec<W>, ParseError> {
+    let mut text = String::new();
+    while let Some(line) = input.next().await {
+        if line.trim().is_empty() {
+            if text.trim().is_empty() {
+                // Keeps line numbers of errors right
+                text.push('\n');
+                continue;
+            }
+            break;
+        }
+
+        text += &line;
+        text.push('\n');
+    }
+
+    parse(&text)
+}
+
+/// Loads program from file
+pub fn load<W: Word>(path: impl AsRef<Path>) -> io::Result<Vec<W>> {
+    parse(&fs::read_to_string(path)?).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load, parse, read, ParseError, ParseErrorKind};
+    use async_std::prelude::*;
+    use async_std::stream::from_iter;
+    use std::env::temp_dir;
+    use std::fs;
+
+    #[async_std::test]
+    async fn parse_test() -> std::io::Result<()> {
+        assert_eq!(Ok(vec![1, 0, 0, 99]), parse::<i64>("1,0,0,99\n"));
+        assert_eq!(
+            Ok(vec![1, -2, 3, 4, 99]),
+            parse::<i64>("  1, -2,\n\t3 ,4,\r\n99,\n\n")
+        );
+
+        let error = |line, column, kind| Err(ParseError { line, column, kind });
+        assert_eq!(
+            error(2, 3, ParseErrorKind::InvalidValue("x7".to_owned())),
+            parse::<i64>("1,2,\n3,x7,99")
+        );
+        assert_eq!(
+            error(1, 3, ParseErrorKind::MissingValue),
+            parse::<i64>("1,,2")
+        );
+        assert_eq!(
+            error(1, 1, ParseErrorKind::MissingValue),
+            parse::<i64>(",1")
+        );
+        assert_eq!(
+            error(1, 1, ParseErrorKind::EmptyProgram),
+            parse::<i64>(" \n")
+        );
+        assert_eq!(
+            "line 1, column 4: invalid value `1ä`",
+            parse::<i64>("99,1ä").unwrap_err().to_string()
+        );
+
+        // Program ends with blank line, values may be split between lines
+        // with or without commas
+        let lines =
+            |lines: &[&str]| from_iter(lines.iter().map(|l| l.to_string()).collect::<Vec<_>>());
+        let mut input = lines(&["", "1,2,", "3", "4", " ", "5"]);
+        assert_eq!(Ok(vec![1, 2, 3, 4]), read::<i128, _>(&mut input).await);
+        assert_eq!(Some("5".to_owned()), input.next().await);
+        let mut input = lines(&["1 0 0 0", "99"]);
+        assert_eq!(Ok(vec![1, 0, 0, 0, 99]), read::<i128, _>(&mut input).await);
+        let mut input = lines(&["", "1,", "x"]);
+        assert_eq!(
+            error(3, 1, ParseErrorKind::InvalidValue("x".to_owned())),
+            read::<i64, _>(&mut input).await
+        );
+
+        let path = temp_dir().join("intcode_parse_test");
+        fs::write(&path, "104,\n7,\n99\n").unwrap();
+        assert_eq!(vec![104, 7, 99], load::<i128>(&path).unwrap());
+        fs::write(&path, "104,7,9a\n").unwrap();
+        assert_eq!(
+            "line 1, column 7: invalid value `9a`",
+            load::<i128>(&path).unwrap_err().to_string()
+        );
+        fs::remove_file(&path).unwrap();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+use super::disasm::decode_line;
+use super::memory::Cells;
+use super::Op;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+/// Execution statistics collected by `Machine` since profiling was enabled
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Profile {
+    // Executions by instruction address, including `EXIT`
+    hits: HashMap<usize, u64>,
+    // Executions by opcode, indexed by `Op::code`
+    ops: [u64; 10],
+    // Taken jumps to non-greater address as (pc, target)
+    back_edges: BTreeMap<(usize, usize), u64>,
+    instructions: u64,
+    peak_memory: usize,
+}
+
+impl Profile {
+    pub(super) fn new(memory: usize) -> Self {
+        Self {
+            peak_memory: memory,
+            ..Self::default()
+        }
+    }
+
+    /// Called for every performed instruction, `memory` is number of cells
+    /// allocated after it
+    pub(super) fn record(&mut self, pc: usize, op: Op, new_pc: usize, memory: usize) {
+        *self.hits.entry(pc).or_insert(0) += 1;
+        self.ops[op.code() as usize] += 1;
+        if new_pc <= pc {
+            *self.back_edges.entry((pc, new_pc)).or_insert(0) += 1;
+        }
+        self.instructions += 1;
+        self.peak_memory = self.peak_memory.max(memory);
+    }
+
+    pub(super) fn record_exit(&mut self, pc: usize) {
+        *self.hits.entry(pc).or_insert(0) += 1;
+    }
+
+    /// Number of performed instructions, `EXIT` is not counted
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    /// Maximal number of allocated memory cells
+    pub fn peak_memory(&self) -> usize {
+        self.peak_memory
+    }
+
+    /// Executions of instruction at given address
+    pub fn hits(&self, pc: usize) -> u64 {
+        self.hits.get(&pc).copied().unwrap_or(0)
+    }
+
+    /// Executed addresses with their executions, most executed first
+    pub fn hot_spots(&self) -> Vec<(usize, u64)> {
+        let mut spots: Vec<_> = self.hits.iter().map(|(pc, n)| (*pc, *n)).collect();
+        spots.sort_by_key(|(pc, n)| (Reverse(*n), *pc));
+        spots
+    }
+
+    /// Executions of every instruction kind, in opcode order
+    pub fn ops(&self) -> Vec<(Op, u64)> {
+        (1..self.ops.len())
+            .filter_map(|code| Op::new(code as i128))
+            .map(|op| (op, self.ops[op.code() as usize]))
+            .collect()
+    }
+
+    /// Taken backward jumps as ((pc, target), count), most taken first.
+    /// Every such jump closes a loop.
+    pub fn back_edges(&self) -> Vec<((usize, usize), u64)> {
+        let mut edges: Vec<_> = self.back_edges.iter().map(|(e, n)| (*e, *n)).collect();
+        edges.sort_by_key(|(_, n)| Reverse(*n));
+        edges
+    }
+
+    /// Human readable report, with at most `limit` hot spots disassembled
+    /// from `program`
+    pub fn report(&self, program: &(impl Cells + ?Sized), limit: usize) -> String {
+        let percent = |n: u64| 100.0 * n as f64 / self.instructions.max(1) as f64;
+
+        let mut report = format!(
+            "Executed {} instructions, peak memory {} cells\n\nHot spots:\n",
+            self.instructions, self.peak_memory
+        );
+        for (pc, n) in self.hot_spots().into_iter().take(limit) {
+            let line = decode_line(program, pc);
+            writeln!(report, "{:10} {:6.2}% {}", n, percent(n), line).unwrap();
+        }
+
+        report += "\nInstructions:\n";
+        for (op, n) in self.ops() {
+            writeln!(report, "{:10} {:6.2}% {}", n, percent(n), op.mnemonic()).unwrap();
+        }
+
+        report += "\nLoops:\n";
+        for ((pc, target), n) in self.back_edges() {
+            writeln!(report, "{:10} {:4} -> {}", n, pc, target).unwrap();
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::asm::assemble;
+    use crate::intcode::{Machine, Op, State};
+
+    #[test]
+    fn profile_test() {
+        // Outputs sum of numbers from input down to 1
+        let program = assemble(
+            "
+                    READ [n]
+            loop:   ADD [sum], [n], [sum]
+                    ADD [n], #-1, [n]
+                    JMPT [n], #loop
+                    WRT [sum]
+                    EXIT
+            n:      DATA 0
+            sum:    DATA 0
+            ",
+        )
+        .unwrap();
+
+        let mut machine: Machine = Machine::new(program.clone());
+        machine.enable_profiling();
+        machine.input(10);
+        assert_eq!(Ok(State::Output(55)), machine.run_until_event());
+        assert_eq!(Ok(State::Halted), machine.run_until_event());
+        assert_eq!(Ok(State::Halted), machine.step());
+
+        let profile = machine.profile().unwrap();
+        assert_eq!(32, profile.instructions());
+        assert_eq!(program.len(), profile.peak_memory());
+        assert_eq!(1, profile.hits(13));
+        assert_eq!(
+            vec![(2, 10), (6, 10), (10, 10), (0, 1), (13, 1), (15, 1)],
+            profile.hot_spots()
+        );
+        assert_eq!(Some(&(Op::Add, 20)), profile.ops().first());
+        assert_eq!(vec![((10, 2), 9)], profile.back_edges());
+
+        let report = profile.report(machine.memory(), 2);
+        assert!(report.starts_with(
+            "Executed 32 instructions, peak memory 18 cells\n\n\
+             Hot spots:\n        \
+             10  31.25%    2: [    1] ADD   Pos(17)  Pos(16)  Pos(17)\n"
+        ));
+        assert!(report.contains("\n        20  62.50% ADD\n"));
+        assert!(report.ends_with("\nLoops:\n         9   10 -> 2\n"));
+    }
+}
@@ -0,0 +1,205 @@
+use super::disasm::{disassemble, Line};
+use super::memory::Memory;
+use super::word::Word;
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+
+/// Why written cell is considered to be code
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CodeKind {
+    /// Cell was a part of already executed instruction
+    Executed,
+    /// Cell was a part of instruction found by static analysis of program
+    Decoded,
+}
+
+/// Single write into code
+#[derive(Debug, Clone, PartialEq)]
+pub struct Modification<W = i128> {
+    pub addr: usize,
+    /// Address of writing instruction, `None` for writes from outside of
+    /// program (`Machine::poke`)
+    pub pc: Option<usize>,
+    /// Number of instructions executed before the write
+    pub executed: u64,
+    pub kind: CodeKind,
+    pub old: W,
+    pub new: W,
+}
+
+/// Every write into code performed since analysis was enabled
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report<W = i128> {
+    modifications: Vec<Modification<W>>,
+}
+
+impl<W> Report<W> {
+    pub fn modifications(&self) -> &[Modification<W>] {
+        &self.modifications
+    }
+
+    /// Modified addresses, in order
+    pub fn addresses(&self) -> BTreeSet<usize> {
+        self.modifications.iter().map(|m| m.addr).collect()
+    }
+
+    /// Checks if code was never modified by program itself, so static
+    /// analysis and caching decoded instructions is safe
+    pub fn is_clean(&self) -> bool {
+        self.modifications.iter().all(|m| m.pc.is_none())
+    }
+}
+
+impl<W: Word> fmt::Display for Report<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifications.is_empty() {
+            return writeln!(f, "No code modifications");
+        }
+
+        for m in &self.modifications {
+            let kind = match m.kind {
+                CodeKind::Executed => "executed",
+                CodeKind::Decoded => "decoded",
+            };
+            let pc = match m.pc {
+                Some(pc) => format!("pc {}", pc),
+                None => "poke".to_owned(),
+            };
+
+            writeln!(
+                f,
+                "{:4}: {} -> {} by {} after {} instructions ({})",
+                m.addr, m.old, m.new, pc, m.executed, kind
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+pub(super) struct Tracker<W> {
+    // Cells of statically decoded instructions
+    decoded: HashSet<usize>,
+    // Cells of executed instructions
+    executed: HashSet<usize>,
+    report: Report<W>,
+}
+
+impl<W: Word> Tracker<W> {
+    pub(super) fn new(memory: &Memory<W>) -> Self {
+        let image: Vec<_> = memory.image().iter().map(Word::clamp_i128).collect();
+        let decoded = disassemble(&image)
+            .lines()
+            .iter()
+            .filter(|line| !matches!(line, Line::Data { .. }))
+            .flat_map(|line| line.addr()..line.addr() + line.size())
+            .collect();
+
+        Self {
+            decoded,
+            executed: HashSet::new(),
+            report: Report {
+                modifications: vec![],
+            },
+        }
+    }
+
+    pub(super) fn report(&self) -> &Report<W> {
+        &self.report
+    }
+
+    /// Called for every instruction before it is performed
+    pub(super) fn execute(&mut self, pc: usize, size: usize) {
+        // Last cell may be at the top address, so range is inclusive
+        self.executed.extend(pc..=pc + (size - 1));
+    }
+
+    pub(super) fn write(
+        &mut self,
+        pc: Option<usize>,
+        executed: u64,
+        addr: usize,
+        old: &W,
+        new: &W,
+    ) {
+        let kind = if self.executed.contains(&addr) {
+            CodeKind::Executed
+        } else if self.decoded.contains(&addr) {
+            CodeKind::Decoded
+        } else {
+            return;
+        };
+
+        self.report.modifications.push(Modification {
+            addr,
+            pc,
+            executed,
+            kind,
+            old: old.clone(),
+            new: new.clone(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CodeKind, Modification};
+    use crate::intcode::{Machine, State};
+
+    #[test]
+    fn selfmod_test() {
+        // Day 2 example: patched by caller, then overwriting its own first
+        // instruction
+        let mut machine: Machine = Machine::new(vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]);
+        machine.analyze_self_modification();
+        machine.poke(9, 30);
+        machine.poke(1, 9);
+        assert_eq!(Ok(State::Halted), machine.run_until_event());
+
+        let report = machine.self_modification().unwrap();
+        assert_eq!(
+            &[
+                Modification {
+                    addr: 1,
+                    pc: None,
+                    executed: 0,
+                    kind: CodeKind::Decoded,
+                    old: 9,
+                    new: 9,
+                },
+                Modification {
+                    addr: 3,
+                    pc: Some(0),
+                    executed: 0,
+                    kind: CodeKind::Executed,
+                    old: 3,
+                    new: 70,
+                },
+                Modification {
+                    addr: 0,
+                    pc: Some(4),
+                    executed: 1,
+                    kind: CodeKind::Executed,
+                    old: 1,
+                    new: 3500,
+                },
+            ][..],
+            report.modifications()
+        );
+        assert!(!report.is_clean());
+        assert_eq!(
+            "   1: 9 -> 9 by poke after 0 instructions (decoded)\n   \
+             3: 3 -> 70 by pc 0 after 0 instructions (executed)\n   \
+             0: 1 -> 3500 by pc 4 after 1 instructions (executed)\n",
+            report.to_string()
+        );
+
+        // Day 9 quine only writes beyond its code
+        let mut machine: Machine = Machine::new(vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ]);
+        machine.analyze_self_modification();
+        while let Ok(State::Output(_)) = machine.run_until_event() {}
+        assert!(machine.self_modification().unwrap().is_clean());
+    }
+}
@@ -0,0 +1,221 @@
+use super::ascii::{encode, Ascii, Decoder};
+use super::{Machine, State};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const HELP: &str = "\
+!history        list commands sent so far
+!N              send command number N from history again
+!save PATH      save commands sent so far
+!load PATH      restart program and send commands from file
+!quit           exit session
+Any other line is sent to the program.";
+
+/// Interactive session with ASCII program. Lines are executed one by one,
+/// every line returns text to be presented to user. Lines starting with `!`
+/// control the session itself, any other is sent to the program.
+///
+/// Session is saved as list of commands sent, one per line, so the same
+/// file may serve as a script of commands to send before interacting.
+pub struct Session {
+    program: Vec<i128>,
+    machine: Machine,
+    decoder: Decoder,
+    history: Vec<String>,
+    halted: bool,
+}
+
+impl Session {
+    pub fn new(program: Vec<i128>) -> Self {
+        Self {
+            machine: Machine::new(program.clone()),
+            program,
+            decoder: Decoder::new(),
+            history: vec![],
+            halted: false,
+        }
+    }
+
+    /// Runs the program until it asks for first command, returns its output
+    pub fn start(&mut self) -> String {
+        self.run()
+    }
+
+    /// Commands sent to program so far
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Executes single line. Returns `None` if session should be finished.
+    pub fn execute(&mut self, line: &str) -> Option<String> {
+        let line = line.trim();
+        let mut words = line.splitn(2, ' ');
+
+        let res = match (words.next(), words.next().map(str::trim)) {
+            (Some("!quit"), None) => return None,
+            (Some("!help"), None) => Ok(HELP.to_owned()),
+            (Some("!history"), None) => Ok(self.list_history()),
+            (Some("!save"), Some(path)) => self.save(path).map_err(|err| err.to_string()),
+            (Some("!load"), Some(path)) => self.load(path).map_err(|err| err.to_string()),
+            (Some(cmd), None) if cmd.starts_with('!') => match cmd[1..].parse::<usize>() {
+                Ok(n) if n > 0 && n <= self.history.len() => {
+                    let command = self.history[n - 1].clone();
+                    Ok(self.send(&command))
+                }
+                _ => Err(format!("Unknown command: {}, try `!help`", line)),
+            },
+            _ if line.starts_with('!') => Err(format!("Unknown command: {}, try `!help`", line)),
+            _ => Ok(self.send(line)),
+        };
+
+        Some(res.unwrap_or_else(|err| err))
+    }
+
+    /// Sends command to the program, returns its output
+    pub fn send(&mut self, command: &str) -> String {
+        if self.halted {
+            return "Program halted".to_owned();
+        }
+
+        encode(command).for_each(|val| self.machine.input(val));
+        self.history.push(command.to_owned());
+        self.run()
+    }
+
+    // Runs until the program needs input or stops, returns its output
+    fn run(&mut self) -> String {
+        let mut out = vec![];
+        loop {
+            match self.machine.run_until_event() {
+                Ok(State::Output(val)) => match self.decoder.push(val) {
+                    Some(Ascii::Line(line)) => out.push(line),
+                    Some(Ascii::Raw(val)) => out.push(format!("Raw value: {}", val)),
+                    None => (),
+                },
+                // Session machine has no watchpoints
+                Ok(State::Running) | Ok(State::Watchpoint(_)) => (),
+                Ok(State::NeedsInput) => break,
+                Ok(State::Halted) => {
+                    self.halted = true;
+                    out.push("Program halted".to_owned());
+                    break;
+                }
+                Err(err) => {
+                    self.halted = true;
+                    out.push(format!("Error: {}", err));
+                    break;
+                }
+            }
+        }
+
+        // Prompts are usually not terminated with newline
+        if let Some(Ascii::Line(line)) = self.decoder.finish::<i128>() {
+            out.push(line);
+        }
+
+        out.join("\n")
+    }
+
+    fn list_history(&self) -> String {
+        self.history
+            .iter()
+            .enumerate()
+            .map(|(idx, command)| format!("{:4}: {}", idx + 1, command))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn save(&self, path: impl AsRef<Path>) -> io::Result<String> {
+        let mut content = self.history.join("\n");
+        content.push('\n');
+        fs::write(path, content)?;
+        Ok(format!("{} commands saved", self.history.len()))
+    }
+
+    /// Restarts the program and sends commands read from file, returns
+    /// whole transcript. Session is unchanged if file cannot be read.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> io::Result<String> {
+        let commands = fs::read_to_string(path)?;
+        *self = Self::new(self.program.clone());
+
+        let mut out = self.start();
+        for command in commands.lines() {
+            out += &format!("\n> {}\n{}", command, self.send(command));
+        }
+
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Session;
+    use crate::intcode::asm::assemble;
+    use std::env::temp_dir;
+    use std::fs;
+
+    #[test]
+    fn session_test() {
+        // Prompts with `?` and echoes lines until empty one is sent, then
+        // outputs 1000
+        let program = assemble(
+            "
+            prompt: WRT #63
+                    READ [c]
+                    EQ [c], #10, [end]
+                    JMPT [end], #done
+            loop:   WRT [c]
+                    READ [c]
+                    EQ [c], #10, [end]
+                    JMPF [end], #loop
+                    WRT #10
+                    JMPT #1, #prompt
+            done:   WRT #1000
+                    EXIT
+            c:      DATA 0
+            end:    DATA 0
+            ",
+        )
+        .unwrap();
+
+        let mut session = Session::new(program);
+        assert_eq!("?", session.start());
+        assert_eq!(Some("north\n?".to_owned()), session.execute("north"));
+        assert_eq!(Some("take\n?".to_owned()), session.execute(" take "));
+        assert_eq!(
+            Some("   1: north\n   2: take".to_owned()),
+            session.execute("!history")
+        );
+        assert_eq!(Some("north\n?".to_owned()), session.execute("!1"));
+        assert_eq!(
+            Some("Unknown command: !4, try `!help`".to_owned()),
+            session.execute("!4")
+        );
+
+        let path = temp_dir().join("intcode_session_test");
+        let path = path.to_str().unwrap();
+        assert_eq!(
+            Some("3 commands saved".to_owned()),
+            session.execute(&format!("!save {}", path))
+        );
+        assert_eq!(
+            Some("Raw value: 1000\nProgram halted".to_owned()),
+            session.execute("")
+        );
+        assert!(session.is_halted());
+
+        assert_eq!(
+            Some("?\n> north\nnorth\n?\n> take\ntake\n?\n> north\nnorth\n?".to_owned()),
+            session.execute(&format!("!load {}", path))
+        );
+        fs::remove_file(path).unwrap();
+        assert!(!session.is_halted());
+        assert_eq!(3, session.history().len());
+        assert_eq!(None, session.execute("!quit"));
+    }
+}
@@ -0,0 +1,221 @@
+use super::memory::{Memory, PAGE_SIZE};
+use super::word::Word;
+use super::Machine;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+const HEADER: &str = "intcode-snapshot 1";
+
+/// Complete state of machine, allowing to resume it later. Snapshot is
+/// serialized to simple line based text format, with contiguous part of
+/// memory in `memory` line, followed by every non-zero sparse page:
+///
+/// ```text
+/// intcode-snapshot 1
+/// pc 12
+/// relative_base 0
+/// input 1,2
+/// memory 1101,1,2,3,99
+/// page 1048576 0,0,7,0,...
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot<W = i128> {
+    pub memory: Memory<W>,
+    pub pc: usize,
+    pub relative_base: isize,
+    /// Input buffered, but not yet consumed by program
+    pub input: Vec<W>,
+}
+
+impl<W: Word> Snapshot<W> {
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        fs::read_to_string(path)?
+            .parse()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+fn join<W: Word>(values: &[W]) -> String {
+    values
+        .iter()
+        .map(W::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+impl<W: Word> fmt::Display for Snapshot<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        writeln!(f, "pc {}", self.pc)?;
+        writeln!(f, "relative_base {}", self.relative_base)?;
+        writeln!(f, "input {}", join(&self.input))?;
+        writeln!(f, "memory {}", join(self.memory.image()))?;
+        for (addr, page) in self.memory.pages() {
+            if page.iter().any(|v| !v.is_zero()) {
+                writeln!(f, "page {} {}", addr, join(page))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn field<'a>(lines: &mut impl Iterator<Item = &'a str>, name: &str) -> Result<&'a str, String> {
+    let line = lines
+        .next()
+        .ok_or_else(|| format!("missing `{}` field", name))?;
+    let mut parts = line.splitn(2, ' ');
+
+    match (parts.next(), parts.next()) {
+        (Some(n), Some(value)) if n == name => Ok(value.trim()),
+        (Some(n), None) if n == name => Ok(""),
+        _ => Err(format!("expected `{}` field, got: {}", name, line)),
+    }
+}
+
+fn values<W: Word>(field: &str) -> Result<Vec<W>, String> {
+    if field.is_empty() {
+        return Ok(vec![]);
+    }
+
+    field
+        .split(',')
+        .map(|v| v.parse().map_err(|_| format!("invalid value: {}", v)))
+        .collect()
+}
+
+impl<W: Word> FromStr for Snapshot<W> {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines();
+        if lines.next() != Some(HEADER) {
+            return Err("not an intcode snapshot".to_owned());
+        }
+
+        let pc = field(&mut lines, "pc")?;
+        let relative_base = field(&mut lines, "relative_base")?;
+        let input = values(field(&mut lines, "input")?)?;
+        let mut memory = Memory::new(values(field(&mut lines, "memory")?)?);
+
+        for line in lines.filter(|line| !line.is_empty()) {
+            let mut parts = line.splitn(3, ' ');
+            if parts.next() != Some("page") {
+                return Err(format!("expected `page` field, got: {}", line));
+            }
+
+            let addr = parts.next().unwrap_or("");
+            let addr: usize = match addr.parse() {
+                Ok(addr) if addr % PAGE_SIZE == 0 => addr,
+                _ => return Err(format!("invalid page address: {}", addr)),
+            };
+
+            let page = values(parts.next().unwrap_or("").trim())?;
+            if page.len() > PAGE_SIZE {
+                return Err(format!("page at {} too long", addr));
+            }
+
+            for (offset, val) in page.into_iter().enumerate() {
+                memory.set(addr + offset, val);
+            }
+        }
+
+        Ok(Self {
+            pc: pc.parse().map_err(|_| format!("invalid pc: {}", pc))?,
+            relative_base: relative_base
+                .parse()
+                .map_err(|_| format!("invalid relative base: {}", relative_base))?,
+            input,
+            memory,
+        })
+    }
+}
+
+impl<W: Word> Machine<W> {
+    /// Captures complete machine state
+    pub fn snapshot(&self) -> Snapshot<W> {
+        Snapshot {
+            memory: self.memory.clone(),
+            pc: self.pc,
+            relative_base: self.relative_base,
+            input: self.input.iter().cloned().collect(),
+        }
+    }
+
+    /// Creates machine resuming from captured state
+    pub fn restore(snapshot: &Snapshot<W>) -> Self {
+        let mut machine = Machine::new(vec![]);
+        machine.memory = snapshot.memory.clone();
+        machine.pc = snapshot.pc;
+        machine.relative_base = snapshot.relative_base;
+        machine.input = snapshot.input.iter().cloned().collect();
+        machine
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Snapshot;
+    use crate::intcode::{Machine, State};
+    use std::env::temp_dir;
+    use std::fs;
+
+    #[test]
+    fn snapshot_test() {
+        // Outputs sum of every two inputs
+        let mut machine: Machine =
+            Machine::new(vec![3, 13, 3, 14, 1, 13, 14, 15, 4, 15, 1105, 1, 0]);
+        machine.input(1);
+        machine.input(2);
+        machine.input(3);
+        assert_eq!(Ok(State::Output(3)), machine.run_until_event());
+
+        let snapshot = machine.snapshot();
+        assert_eq!(
+            "intcode-snapshot 1\n\
+             pc 10\n\
+             relative_base 0\n\
+             input 3\n\
+             memory 3,13,3,14,1,13,14,15,4,15,1105,1,0,1,2,3\n",
+            snapshot.to_string()
+        );
+        assert_eq!(Ok(snapshot.clone()), snapshot.to_string().parse());
+
+        let path = temp_dir().join("intcode_snapshot_test");
+        snapshot.save(&path).unwrap();
+        let loaded: Snapshot = Snapshot::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        for mut machine in [machine, Machine::restore(&loaded)] {
+            machine.input(4);
+            assert_eq!(Ok(State::Output(7)), machine.run_until_event());
+            assert_eq!(Ok(State::NeedsInput), machine.run_until_event());
+        }
+
+        let mut machine: Machine = Machine::new(vec![99]);
+        machine.poke((1 << 40) + 1, 5);
+        let snapshot = machine.snapshot();
+        assert!(snapshot
+            .to_string()
+            .contains("memory 99\npage 1099511627776 0,5,0,"));
+        assert_eq!(Ok(snapshot.clone()), snapshot.to_string().parse());
+
+        // Page written and zeroed again is not saved
+        machine.poke(1 << 41, 7);
+        machine.poke(1 << 41, 0);
+        let snapshot = machine.snapshot();
+        assert_eq!(Ok(snapshot.clone()), snapshot.to_string().parse());
+
+        assert_eq!(
+            Err("expected `pc` field, got: relative_base 0".to_owned()),
+            "intcode-snapshot 1\nrelative_base 0".parse::<Snapshot>()
+        );
+    }
+}
@@ -0,0 +1,269 @@
+use super::word::Word;
+use super::{Argument, Op};
+use lazy_static::lazy_static;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{self, stderr, LineWriter, Write};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Environment variable selecting tracer used by newly created machines.
+/// All machines share single output, events are told apart by machine id:
+/// * `text` - human readable trace on stderr
+/// * `text:<path>` - human readable trace appended to file
+/// * `json:<path>` - JSON lines appended to file
+pub const TRACE_VAR: &str = "INTCODE_TRACE";
+
+/// Executed instruction
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event<W = i128> {
+    /// Id of executing machine, see `Machine::id`
+    pub machine: usize,
+    pub pc: usize,
+    /// Raw opcode, including argument modes
+    pub opcode: i128,
+    /// Executed operation, `None` for termination opcode
+    pub op: Option<Op>,
+    pub args: Vec<Argument<W>>,
+    /// Values of arguments read by instruction, in order
+    pub values: Vec<W>,
+    /// Memory write performed by instruction: (address, value)
+    pub write: Option<(usize, W)>,
+}
+
+/// Receiver of executed instructions
+pub trait Tracer<W = i128>: Send {
+    fn trace(&mut self, event: &Event<W>);
+}
+
+/// Human readable trace, one instruction per line
+pub struct TextTracer<O> {
+    out: O,
+}
+
+impl<O: Write> TextTracer<O> {
+    pub fn new(out: O) -> Self {
+        Self { out }
+    }
+}
+
+impl<O: Write + Send, W: Word> Tracer<W> for TextTracer<O> {
+    fn trace(&mut self, event: &Event<W>) {
+        let mut line = format!("#{} {:4}: [{:5}] ", event.machine, event.pc, event.opcode);
+        match event.op {
+            Some(op) => line += &format!("{:5}", op.mnemonic()),
+            None => line += "EXIT",
+        }
+
+        let mut values = event.values.iter();
+        for (idx, arg) in event.args.iter().enumerate() {
+            line += &format!("  {:?}", arg);
+            if event.op.and_then(|op| op.output_arg()) != Some(idx) {
+                if let Some(v) = values.next() {
+                    line += &format!("[{}]", v);
+                }
+            }
+        }
+
+        if let Some((addr, val)) = &event.write {
+            line += &format!("  -> {} = {}", addr, val);
+        }
+
+        // Whole line is written at once, so lines of machines sharing output
+        // are never mixed. Tracing should never break traced program.
+        line.push('\n');
+        self.out.write_all(line.as_bytes()).ok();
+    }
+}
+
+/// JSON lines trace, one object per instruction
+pub struct JsonTracer<O> {
+    out: O,
+}
+
+impl<O: Write> JsonTracer<O> {
+    pub fn new(out: O) -> Self {
+        Self { out }
+    }
+}
+
+fn json_arg<W: Word>(arg: &Argument<W>) -> String {
+    match arg {
+        Argument::Imm(v) => format!(r#"{{"mode":"imm","value":{}}}"#, v),
+        Argument::Pos(v) => format!(r#"{{"mode":"pos","value":{}}}"#, v),
+        Argument::Rel(v) => format!(r#"{{"mode":"rel","value":{}}}"#, v),
+    }
+}
+
+impl<O: Write + Send, W: Word> Tracer<W> for JsonTracer<O> {
+    fn trace(&mut self, event: &Event<W>) {
+        let args: Vec<_> = event.args.iter().map(json_arg).collect();
+        let values: Vec<_> = event.values.iter().map(W::to_string).collect();
+        let write = match &event.write {
+            Some((addr, val)) => format!(r#"{{"addr":{},"value":{}}}"#, addr, val),
+            None => "null".to_owned(),
+        };
+
+        let line = format!(
+            r#"{{"machine":{},"pc":{},"opcode":{},"op":"{}","args":[{}],"values":[{}],"write":{}}}"#,
+            event.machine,
+            event.pc,
+            event.opcode,
+            event.op.map_or("EXIT", |op| op.mnemonic()),
+            args.join(","),
+            values.join(","),
+            write
+        );
+        self.out.write_all(format!("{}\n", line).as_bytes()).ok();
+    }
+}
+
+fn append(path: &str) -> io::Result<LineWriter<Box<dyn Write + Send>>> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    Ok(LineWriter::new(Box::new(file)))
+}
+
+// Output shared by tracers of many machines
+#[derive(Clone)]
+struct Shared(Arc<Mutex<LineWriter<Box<dyn Write + Send>>>>);
+
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut out = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        out.write(buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        let mut out = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        out.write_all(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut out = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        out.flush()
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    Text,
+    Json,
+}
+
+impl Format {
+    fn tracer<W: Word>(self, out: impl Write + Send + 'static) -> Box<dyn Tracer<W>> {
+        match self {
+            Self::Text => Box::new(TextTracer::new(out)),
+            Self::Json => Box::new(JsonTracer::new(out)),
+        }
+    }
+}
+
+// Parses specification, opening its output
+fn open(spec: &str) -> Result<(Format, LineWriter<Box<dyn Write + Send>>), String> {
+    let (kind, path) = match spec.find(':') {
+        Some(idx) => (&spec[..idx], Some(&spec[idx + 1..])),
+        None => (spec, None),
+    };
+
+    let format = match (kind, path) {
+        ("text", None) => return Ok((Format::Text, LineWriter::new(Box::new(stderr())))),
+        ("text", Some(_)) => Format::Text,
+        ("json", Some(_)) => Format::Json,
+        _ => return Err(format!("Invalid tracer specification: {}", spec)),
+    };
+
+    let path = path.unwrap_or_default();
+    let out = append(path).map_err(|err| format!("{}: {}", path, err))?;
+    Ok((format, out))
+}
+
+lazy_static! {
+    // Environment is read and output opened once, not for every created
+    // machine
+    static ref ENV_OUTPUT: Option<(Format, Shared)> = env::var(TRACE_VAR).ok().and_then(|spec| {
+        open(&spec)
+            .map(|(format, out)| (format, Shared(Arc::new(Mutex::new(out)))))
+            .map_err(|err| eprintln!("{}: {}, tracing disabled", TRACE_VAR, err))
+            .ok()
+    });
+}
+
+/// Creates tracer according to specification in format of `TRACE_VAR`,
+/// with its own output
+pub fn from_spec<W: Word>(spec: &str) -> Result<Box<dyn Tracer<W>>, String> {
+    let (format, out) = open(spec)?;
+    Ok(format.tracer(out))
+}
+
+/// Creates tracer selected by `TRACE_VAR` environment variable, if any.
+/// Variable is read only on first call, and all tracers created by this
+/// function write to the same output.
+pub fn from_env<W: Word>() -> Option<Box<dyn Tracer<W>>> {
+    let (format, out) = ENV_OUTPUT.as_ref()?;
+    Some(format.tracer(out.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_spec, open, Event, JsonTracer, Shared, TextTracer, Tracer};
+    use crate::intcode::{Argument, Op};
+    use std::env::temp_dir;
+    use std::fs;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn tracers_test() {
+        let event: Event = Event {
+            machine: 3,
+            pc: 4,
+            opcode: 1001,
+            op: Some(Op::Add),
+            args: vec![Argument::Pos(10), Argument::Imm(-1), Argument::Rel(2)],
+            values: vec![5, -1],
+            write: Some((7, 4)),
+        };
+
+        let mut text = vec![];
+        TextTracer::new(&mut text).trace(&event);
+        assert_eq!(
+            "#3    4: [ 1001] ADD    Pos(10)[5]  Imm(-1)[-1]  Rel(2)  -> 7 = 4\n",
+            String::from_utf8(text).unwrap()
+        );
+
+        let mut json = vec![];
+        JsonTracer::new(&mut json).trace(&event);
+        assert_eq!(
+            concat!(
+                r#"{"machine":3,"pc":4,"opcode":1001,"op":"ADD","#,
+                r#""args":[{"mode":"pos","value":10},{"mode":"imm","value":-1},{"mode":"rel","value":2}],"#,
+                r#""values":[5,-1],"write":{"addr":7,"value":4}}"#,
+                "\n"
+            ),
+            String::from_utf8(json).unwrap()
+        );
+
+        // Tracers of different machines sharing output
+        let path = temp_dir().join("intcode_trace_test");
+        let (format, out) = open(&format!("text:{}", path.display())).unwrap();
+        let out = Shared(Arc::new(Mutex::new(out)));
+        let mut tracers: Vec<Box<dyn Tracer>> =
+            vec![format.tracer(out.clone()), format.tracer(out)];
+        for machine in 0..4 {
+            tracers[machine % 2].trace(&Event {
+                machine,
+                write: None,
+                ..event.clone()
+            });
+        }
+        let trace = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let machines: Vec<_> = trace.lines().map(|line| &line[..2]).collect();
+        assert_eq!(vec!["#0", "#1", "#2", "#3"], machines);
+
+        assert!(from_spec::<i128>("text").is_ok());
+        assert_eq!(
+            Some("Invalid tracer specification: json".to_owned()),
+            from_spec::<i128>("json").err()
+        );
+    }
+}
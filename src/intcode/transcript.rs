@@ -0,0 +1,229 @@
+use super::word::Word;
+use super::{Machine, State};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+const HEADER: &str = "intcode-transcript 1";
+
+/// Single value passed between machine and its environment, with number of
+/// instructions executed before it
+#[derive(Debug, Clone, PartialEq)]
+pub enum Io<W = i128> {
+    Input(u64, W),
+    Output(u64, W),
+}
+
+/// Every value read and written by machine, in order. Transcript is
+/// serialized to line based text format, one value per line:
+///
+/// ```text
+/// intcode-transcript 1
+/// in 0 5
+/// out 4 10
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transcript<W = i128> {
+    pub events: Vec<Io<W>>,
+}
+
+impl<W> Default for Transcript<W> {
+    fn default() -> Self {
+        Self { events: vec![] }
+    }
+}
+
+impl<W: Word> Transcript<W> {
+    /// Drops values passed after `executed` instructions
+    pub(super) fn rewind(&mut self, executed: u64) {
+        while let Some(Io::Input(at, _)) | Some(Io::Output(at, _)) = self.events.last() {
+            if *at < executed {
+                break;
+            }
+            self.events.pop();
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        fs::read_to_string(path)?
+            .parse()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Runs machine through recorded session, feeding it with recorded
+    /// inputs whenever it asks for them. Session may be interrupted, so
+    /// after last recorded value machine is expected to either halt or ask
+    /// for more input; its final state is returned. Fails on first
+    /// difference between recorded session and replayed one, either in
+    /// values or in moments they are passed at.
+    pub fn replay(&self, machine: &mut Machine<W>) -> Result<State<W>, String> {
+        let mut events = self.events.iter();
+
+        loop {
+            let state = machine
+                .run_until_event()
+                .map_err(|err| format!("program failed: {}", err))?;
+            let event = events.next();
+
+            match (state, event) {
+                (State::NeedsInput, Some(Io::Input(at, val))) => {
+                    let executed = machine.executed();
+                    if executed != *at {
+                        return Err(format!(
+                            "input requested after {} instructions, recorded after {}",
+                            executed, at
+                        ));
+                    }
+                    machine.input(val.clone());
+                }
+                (State::Output(val), Some(Io::Output(at, expected))) => {
+                    // Output instruction is already counted
+                    let executed = machine.executed() - 1;
+                    if (executed, &val) != (*at, expected) {
+                        return Err(format!(
+                            "output {} after {} instructions, recorded {} after {}",
+                            val, executed, expected, at
+                        ));
+                    }
+                }
+                (State::Halted, None) => return Ok(State::Halted),
+                (State::NeedsInput, None) => return Ok(State::NeedsInput),
+                (state, event) => {
+                    return Err(format!(
+                        "unexpected {:?} after {} instructions, recorded {:?}",
+                        state,
+                        machine.executed(),
+                        event
+                    ))
+                }
+            }
+        }
+    }
+}
+
+impl<W: Word> fmt::Display for Transcript<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        for event in &self.events {
+            match event {
+                Io::Input(at, val) => writeln!(f, "in {} {}", at, val)?,
+                Io::Output(at, val) => writeln!(f, "out {} {}", at, val)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<W: Word> FromStr for Transcript<W> {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines();
+        if lines.next() != Some(HEADER) {
+            return Err("not an intcode transcript".to_owned());
+        }
+
+        let events = lines
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let parts: Vec<_> = line.split_whitespace().collect();
+                let (kind, at, val) = match parts.as_slice() {
+                    [kind, at, val] => (kind, at, val),
+                    _ => return Err(format!("invalid event: {}", line)),
+                };
+
+                let at = at
+                    .parse()
+                    .map_err(|_| format!("invalid instruction count: {}", at))?;
+                let val = val.parse().map_err(|_| format!("invalid value: {}", val))?;
+
+                match *kind {
+                    "in" => Ok(Io::Input(at, val)),
+                    "out" => Ok(Io::Output(at, val)),
+                    _ => Err(format!("invalid event: {}", line)),
+                }
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { events })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Io, Transcript};
+    use crate::intcode::{Machine, State};
+
+    #[test]
+    fn transcript_test() {
+        // Outputs sum of every two inputs
+        let program = vec![3, 13, 3, 14, 1, 13, 14, 15, 4, 15, 1105, 1, 0];
+        let mut machine: Machine = Machine::new(program.clone());
+        machine.record_transcript();
+        for input in 1..=3 {
+            while let Ok(State::Output(_)) = machine.run_until_event() {}
+            machine.input(input);
+        }
+        assert_eq!(Ok(State::NeedsInput), machine.run_until_event());
+
+        let mut transcript = machine.transcript().unwrap().clone();
+        assert_eq!(
+            vec![
+                Io::Input(0, 1),
+                Io::Input(1, 2),
+                Io::Output(3, 3),
+                Io::Input(5, 3)
+            ],
+            transcript.events
+        );
+        assert_eq!(
+            "intcode-transcript 1\nin 0 1\nin 1 2\nout 3 3\nin 5 3\n",
+            transcript.to_string()
+        );
+        assert_eq!(
+            Ok(transcript.clone()),
+            transcript.to_string().parse::<Transcript>()
+        );
+
+        // Interrupted session, replayed machine waits for input
+        let mut replayed = Machine::new(program.clone());
+        assert_eq!(Ok(State::NeedsInput), transcript.replay(&mut replayed));
+        assert_eq!(6, replayed.executed());
+
+        // Session interrupted earlier than replayed one
+        let mut replayed = Machine::new(program.clone());
+        let mut truncated = transcript.clone();
+        truncated.events.truncate(2);
+        assert_eq!(
+            Err("unexpected Output(3) after 4 instructions, recorded None".to_owned()),
+            truncated.replay(&mut replayed)
+        );
+
+        let mut replayed = Machine::new(program.clone());
+        transcript.events[2] = Io::Output(3, 4);
+        assert_eq!(
+            Err("output 3 after 3 instructions, recorded 4 after 3".to_owned()),
+            transcript.replay(&mut replayed)
+        );
+
+        // Halting session
+        let program = vec![3, 9, 1002, 9, 2, 9, 4, 9, 99, 0];
+        let mut machine: Machine = Machine::new(program.clone());
+        machine.record_transcript();
+        machine.input(21);
+        assert_eq!(Ok(State::Output(42)), machine.run_until_event());
+        assert_eq!(Ok(State::Halted), machine.run_until_event());
+        let transcript = machine.transcript().unwrap();
+        assert_eq!(
+            Ok(State::Halted),
+            transcript.replay(&mut Machine::new(program))
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use super::word::Word;
+use std::ops::Range;
+
+/// Kind of memory access watched
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Access {
+    /// Cell read as instruction argument in position or relative mode
+    Read,
+    /// Cell written by program, even if its value stays the same
+    Write,
+    /// Cell written by program with value different than previous one
+    Change,
+}
+
+/// Watched access, reported to watchpoint callback
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit<W = i128> {
+    /// Address of accessing instruction
+    pub pc: usize,
+    pub addr: usize,
+    pub access: Access,
+    /// Value before access
+    pub old: W,
+    /// Value after access, same as `old` for reads
+    pub new: W,
+}
+
+/// What machine should do after watchpoint is hit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Continue,
+    /// Machine reports `State::Watchpoint` after accessing instruction is
+    /// performed
+    Stop,
+}
+
+pub type Callback<W> = Box<dyn FnMut(&Hit<W>) -> Action + Send>;
+
+struct Watchpoint<W> {
+    id: usize,
+    range: Range<usize>,
+    access: Access,
+    callback: Callback<W>,
+}
+
+pub(super) struct Watchpoints<W> {
+    watchpoints: Vec<Watchpoint<W>>,
+    next_id: usize,
+    // Stop delayed to not lose output of stopping instruction
+    pub(super) pending: Option<usize>,
+}
+
+impl<W: Word> Watchpoints<W> {
+    pub(super) fn new() -> Self {
+        Self {
+            watchpoints: vec![],
+            next_id: 0,
+            pending: None,
+        }
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.watchpoints.is_empty()
+    }
+
+    pub(super) fn add(
+        &mut self,
+        range: Range<usize>,
+        access: Access,
+        callback: Callback<W>,
+    ) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.watchpoints.push(Watchpoint {
+            id,
+            range,
+            access,
+            callback,
+        });
+        id
+    }
+
+    pub(super) fn remove(&mut self, id: usize) -> bool {
+        let len = self.watchpoints.len();
+        self.watchpoints.retain(|w| w.id != id);
+        self.watchpoints.len() != len
+    }
+
+    /// Calls callbacks of every watchpoint hit by instruction at `pc`,
+    /// which read `reads` as (addr, value) and performed `write` as
+    /// (addr, old, new). Returns first address for which execution should
+    /// stop.
+    pub(super) fn check(
+        &mut self,
+        pc: usize,
+        reads: &[(usize, W)],
+        write: Option<(usize, W, W)>,
+    ) -> Option<usize> {
+        let reads = reads
+            .iter()
+            .map(|(addr, val)| (*addr, Access::Read, val, val));
+        let writes = write.iter().flat_map(|(addr, old, new)| {
+            let change = Some((*addr, Access::Change, old, new)).filter(|_| old != new);
+            Some((*addr, Access::Write, old, new))
+                .into_iter()
+                .chain(change)
+        });
+
+        let mut stop = None;
+        for (addr, access, old, new) in reads.chain(writes) {
+            for watchpoint in &mut self.watchpoints {
+                if watchpoint.access != access || !watchpoint.range.contains(&addr) {
+                    continue;
+                }
+
+                let hit = Hit {
+                    pc,
+                    addr,
+                    access,
+                    old: old.clone(),
+                    new: new.clone(),
+                };
+                if (watchpoint.callback)(&hit) == Action::Stop {
+                    stop = stop.or(Some(addr));
+                }
+            }
+        }
+
+        stop
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Access, Action, Hit};
+    use crate::intcode::{Machine, State};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn watch_test() {
+        // Outputs 1 if input equals 8, 0 otherwise; input is stored in 9
+        let program = vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+        let mut machine: Machine = Machine::new(program);
+
+        let hits = Arc::new(Mutex::new(vec![]));
+        let recorded = hits.clone();
+        machine.watch(
+            9..11,
+            Access::Read,
+            Box::new(move |hit| {
+                recorded.lock().unwrap().push(hit.clone());
+                Action::Continue
+            }),
+        );
+        let change = machine.watch(9..10, Access::Change, Box::new(|_| Action::Stop));
+
+        machine.input(8);
+        assert_eq!(Ok(State::Watchpoint(9)), machine.run_until_event());
+        assert_eq!((2, 8), (machine.pc(), machine.peek(9)));
+        assert!(machine.unwatch(change));
+        assert!(!machine.unwatch(change));
+
+        // Stopping on output instruction doesn't lose output
+        assert_eq!(Ok(State::Running), machine.step());
+        machine.watch(9..10, Access::Read, Box::new(|_| Action::Stop));
+        assert_eq!(Ok(State::Output(1)), machine.run_until_event());
+        assert_eq!(Ok(State::Watchpoint(9)), machine.run_until_event());
+        assert_eq!(8, machine.pc());
+        assert_eq!(Ok(State::Halted), machine.run_until_event());
+
+        let read = |pc, addr, val| Hit {
+            pc,
+            addr,
+            access: Access::Read,
+            old: val,
+            new: val,
+        };
+        assert_eq!(
+            vec![read(2, 9, 8), read(2, 10, 8), read(6, 9, 1)],
+            *hits.lock().unwrap()
+        );
+    }
+}
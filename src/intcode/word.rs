@@ -0,0 +1,141 @@
+use num_bigint::BigInt;
+use num_traits::{ToPrimitive, Zero};
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use std::str::FromStr;
+
+/// Behavior of `Add` and `Mul` when result doesn't fit in machine word
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Overflow {
+    /// Result wraps around word boundaries
+    Wrapping,
+    /// Overflow fails the program with `ErrorKind::Overflow`
+    #[default]
+    Checked,
+    /// Result is clamped to word bounds
+    Saturating,
+}
+
+/// Type of single memory cell of machine
+pub trait Word:
+    Clone + PartialEq + PartialOrd + Hash + Debug + Display + FromStr + Send + Sync + Unpin + 'static
+{
+    fn from_i64(val: i64) -> Self;
+
+    /// Value as `i128`, `None` if it doesn't fit
+    fn to_i128(&self) -> Option<i128>;
+
+    /// Value as `i128`, clamped to its bounds. Used for reporting values in
+    /// errors.
+    fn clamp_i128(&self) -> i128 {
+        self.to_i128().unwrap_or_else(|| {
+            if *self < Self::from_i64(0) {
+                i128::MIN
+            } else {
+                i128::MAX
+            }
+        })
+    }
+
+    fn is_zero(&self) -> bool {
+        *self == Self::from_i64(0)
+    }
+
+    /// Sum of words, `None` on overflow with `Overflow::Checked` policy
+    fn add(&self, rhs: &Self, overflow: Overflow) -> Option<Self>;
+
+    /// Product of words, `None` on overflow with `Overflow::Checked` policy
+    fn mul(&self, rhs: &Self, overflow: Overflow) -> Option<Self>;
+}
+
+macro_rules! primitive_word {
+    ($($t:ty),*) => {$(
+        impl Word for $t {
+            fn from_i64(val: i64) -> Self {
+                val as $t
+            }
+
+            fn to_i128(&self) -> Option<i128> {
+                Some(*self as i128)
+            }
+
+            fn add(&self, rhs: &Self, overflow: Overflow) -> Option<Self> {
+                match overflow {
+                    Overflow::Wrapping => Some(self.wrapping_add(*rhs)),
+                    Overflow::Checked => self.checked_add(*rhs),
+                    Overflow::Saturating => Some(self.saturating_add(*rhs)),
+                }
+            }
+
+            fn mul(&self, rhs: &Self, overflow: Overflow) -> Option<Self> {
+                match overflow {
+                    Overflow::Wrapping => Some(self.wrapping_mul(*rhs)),
+                    Overflow::Checked => self.checked_mul(*rhs),
+                    Overflow::Saturating => Some(self.saturating_mul(*rhs)),
+                }
+            }
+        }
+    )*};
+}
+
+primitive_word!(i64, i128);
+
+/// Arbitrary precision word, overflow policy is meaningless for it
+impl Word for BigInt {
+    fn from_i64(val: i64) -> Self {
+        val.into()
+    }
+
+    fn to_i128(&self) -> Option<i128> {
+        ToPrimitive::to_i128(self)
+    }
+
+    fn is_zero(&self) -> bool {
+        Zero::is_zero(self)
+    }
+
+    fn add(&self, rhs: &Self, _overflow: Overflow) -> Option<Self> {
+        Some(self + rhs)
+    }
+
+    fn mul(&self, rhs: &Self, _overflow: Overflow) -> Option<Self> {
+        Some(self * rhs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Overflow, Word};
+    use crate::intcode::{ErrorKind, Machine, State};
+    use num_bigint::BigInt;
+
+    #[test]
+    fn overflow_test() {
+        // Squares its input
+        let program = vec![3, 7, 2, 7, 7, 7, 104, 0, 99];
+        let run = |overflow, input: i64| {
+            let mut machine = Machine::new(program.clone());
+            machine.set_overflow(overflow);
+            machine.input(input);
+            machine.run_until_event().map_err(|err| err.kind)
+        };
+
+        assert_eq!(Ok(State::Output(9)), run(Overflow::Checked, 3));
+        assert_eq!(Err(ErrorKind::Overflow), run(Overflow::Checked, 1 << 32));
+        assert_eq!(Ok(State::Output(0)), run(Overflow::Wrapping, 1 << 32));
+        assert_eq!(
+            Ok(State::Output(i64::MAX)),
+            run(Overflow::Saturating, 1 << 32)
+        );
+
+        let program = program.into_iter().map(BigInt::from).collect();
+        let mut machine = Machine::new(program);
+        machine.input(BigInt::from(1u64 << 63));
+        assert_eq!(
+            Ok(State::Output(BigInt::from(1u128 << 126))),
+            machine.run_until_event()
+        );
+        assert_eq!(Some(1 << 126), BigInt::from(1u128 << 126).to_i128());
+        assert_eq!(i128::MAX, BigInt::from(1u128 << 127).clamp_i128());
+    }
+}
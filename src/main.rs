@@ -1,11 +1,8 @@
 #![recursion_limit = "256"]
 
-use async_std;
+use aoc_2019::intcode;
 use async_std::io::{stdin, BufReader};
 use async_std::prelude::*;
-use tokio;
-
-mod intcode;
 
 mod day1;
 #[cfg(feature = "day1")]